@@ -0,0 +1,90 @@
+//! Benchmarks for curve arithmetic, key generation and validation
+//!
+//! Run with `cargo bench`. Throughput is reported per key (elements/s = keys/sec).
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use lyssa_rds_gen::crypto::{bigint_to_bytes_le, EllipticCurvePoint};
+use lyssa_rds_gen::keygen::{
+    generate_lkp, generate_spk, generate_tskey, get_spkid, validate_tskey, DEFAULT_MAX_ATTEMPTS,
+};
+use lyssa_rds_gen::types::{KeyKind, LKPCurve, SPKCurve};
+use num_bigint::BigUint;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+const PID: &str = "00490-92005-99454-AT527";
+
+/// Double-and-add using only affine `add`, i.e. one modular inversion per step
+fn mul_affine(point: &EllipticCurvePoint, scalar: &BigUint) -> EllipticCurvePoint {
+    let mut result = EllipticCurvePoint::infinity(point.a.clone(), point.b.clone(), point.p.clone());
+    let mut addend = point.clone();
+    for i in 0..scalar.bits() {
+        if scalar.bit(i) {
+            result = result.add(&addend);
+        }
+        addend = addend.add(&addend);
+    }
+    result
+}
+
+fn bench_mul(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mul");
+    for (name, curve) in [("spk", SPKCurve::params()), ("lkp", LKPCurve::params())] {
+        let g = curve.generator();
+        group.bench_function(name, |b| b.iter(|| g.mul(black_box(&curve.priv_key))));
+        // Baseline for the Jacobian `mul`
+        group.bench_function(format!("{}_affine", name), |b| {
+            b.iter(|| mul_affine(&g, black_box(&curve.priv_key)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_params(c: &mut Criterion) {
+    let mut group = c.benchmark_group("params");
+    group.bench_function("spk", |b| b.iter(SPKCurve::params));
+    group.bench_function("lkp", |b| b.iter(LKPCurve::params));
+    group.finish();
+}
+
+fn bench_generate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generate");
+    group.throughput(Throughput::Elements(1));
+
+    // Seeded nonces keep the number of signing attempts identical across runs
+    let spk_curve = SPKCurve::params();
+    let spkdata = bigint_to_bytes_le(&BigUint::from(get_spkid(PID).unwrap()), 7).unwrap();
+    group.bench_function("spk_seeded", |b| {
+        b.iter(|| {
+            let mut rng = StdRng::seed_from_u64(0);
+            generate_tskey(PID, &spkdata, &spk_curve, KeyKind::Spk, DEFAULT_MAX_ATTEMPTS, &mut rng).unwrap()
+        })
+    });
+
+    group.bench_function("spk", |b| b.iter(|| generate_spk(black_box(PID)).unwrap()));
+    group.bench_function("lkp", |b| {
+        b.iter(|| generate_lkp(black_box(PID), 100, 29, 10, 2).unwrap())
+    });
+    group.finish();
+}
+
+fn bench_validate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("validate");
+    group.throughput(Throughput::Elements(1));
+
+    let spk = generate_spk(PID).unwrap();
+    let lkp = generate_lkp(PID, 100, 29, 10, 2).unwrap();
+    let spk_curve = SPKCurve::params();
+    let lkp_curve = LKPCurve::params();
+
+    group.bench_function("spk", |b| {
+        b.iter(|| validate_tskey(PID, black_box(&spk), &spk_curve, KeyKind::Spk).unwrap())
+    });
+    group.bench_function("lkp", |b| {
+        b.iter(|| validate_tskey(PID, black_box(&lkp), &lkp_curve, KeyKind::Lkp).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_params, bench_mul, bench_generate, bench_validate);
+criterion_main!(benches);
@@ -0,0 +1,59 @@
+#ifndef LYSSA_RDS_GEN_H
+#define LYSSA_RDS_GEN_H
+
+/* Generated with cbindgen from src/ffi.rs (cffi feature). Do not edit by hand. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// Success (for validation: the key is valid)
+#define LYSSA_OK 0
+
+// Validation completed and the key does not match the PID
+#define LYSSA_INVALID_KEY 1
+
+// A required pointer argument was null
+#define LYSSA_ERR_NULL_POINTER -1
+
+// An input string was not valid UTF-8
+#define LYSSA_ERR_INVALID_UTF8 -2
+
+// The output buffer is too small for the result and its terminator
+#define LYSSA_ERR_BUFFER_TOO_SMALL -3
+
+// Generation or validation failed (bad PID, unknown license type, exhausted attempts, internal panic, ...)
+#define LYSSA_ERR_FAILED -4
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Generate an SPK (License Server ID) for `pid` into `out`
+//
+// # Safety
+// `pid` must be a valid C string and `out` valid for writes of `out_len` bytes.
+int lyssa_generate_spk(const char *pid, char *out, size_t out_len);
+
+// Generate an LKP (License Key Pack) for `pid`, `count` and `license` (e.g. `029_10_2`) into `out`
+//
+// # Safety
+// `pid` and `license` must be valid C strings and `out` valid for writes of `out_len` bytes.
+int lyssa_generate_lkp(const char *pid,
+                       uint32_t count,
+                       const char *license,
+                       char *out,
+                       size_t out_len);
+
+// Validate an SPK (`is_spk != 0`) or LKP (`is_spk == 0`) against `pid`
+//
+// Returns `LYSSA_OK` when valid and `LYSSA_INVALID_KEY` when it does not match.
+//
+// # Safety
+// `pid` and `key` must be valid C strings.
+int lyssa_validate_tskey(const char *pid, const char *key, int is_spk);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LYSSA_RDS_GEN_H */
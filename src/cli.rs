@@ -1,14 +1,39 @@
 //! Command-line interface
 
-use crate::keygen::{generate_lkp, generate_spk, validate_tskey};
-use crate::types::{LicenseInfo, SPKCurve, LICENSE_TYPES};
 use clap::Parser;
+use indicatif::{ProgressBar, ProgressStyle};
+use lyssa_rds_gen::crypto::{
+    bigint_to_bytes_le, decode_pkey, encode_pkey, encode_pkey_with, EncodeOptions,
+};
+use lyssa_rds_gen::export::{LicensePackage, PackagedLkp};
+use lyssa_rds_gen::keygen::{
+    derive_spkid, diagnose_tskey, generate_lkp, generate_lkp_rfc6979, generate_lkp_with_curve,
+    generate_spk, generate_spk_many_with_curve, generate_spk_rfc6979, generate_spk_with_curve,
+    generate_tskey_traced, inspect_lkp, normalize_pid, signature_components, validate_pid,
+    validate_tskey, KeyDiagnosis, DEFAULT_MAX_ATTEMPTS,
+};
+use lyssa_rds_gen::types::{
+    find_license, license_code, CurveFile, CurveParams, KeyKind, LKPCurve, LicenseInfo, SPKCurve,
+    KCHARS, LICENSE_TYPES,
+};
+use num_bigint::BigUint;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+use std::fs;
+use std::io::{BufRead, IsTerminal};
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::time::Duration;
 
 #[derive(Parser)]
+#[command(group(clap::ArgGroup::new("outputs").multiple(true).args(["output", "csv", "export"])))]
+#[command(group(clap::ArgGroup::new("mode").args(["pid", "pid_file", "import", "validate_file", "list", "build_info", "version_info", "serve", "socket", "selftest"])))]
+#[command(subcommand_negates_reqs = true)]
 #[command(name = "lyssa_rds_gen")]
 #[command(author = "LyssaRDSGen Contributors")]
 #[command(version = "1.0.0")]
-#[command(about = "Generate RDS License Keys", long_about = "Generate RDS License Keys\n\nRun without arguments or with --gui to launch GUI mode.\nProvide arguments to use CLI mode.")]
+#[command(about = "Generate RDS License Keys", long_about = "Generate RDS License Keys\n\nRun without arguments or with --gui to launch GUI mode.\nProvide arguments to use CLI mode, either as flags or as one of the subcommands below.\n\nOptions marked [env: ...] fall back to that environment variable when the flag is omitted:\nan explicit flag wins over the environment variable, which wins over the built-in default.")]
 pub struct Cli {
     /// Launch GUI mode (graphical interface)
     #[arg(long, conflicts_with = "tui")]
@@ -17,118 +42,1662 @@ pub struct Cli {
     /// Launch TUI mode (terminal interface)
     #[arg(long, conflicts_with = "gui")]
     pub tui: bool,
-    /// Product ID (e.g., 00490-92005-99454-AT527)
+
+    /// Color theme for --tui (cycle at runtime with F3)
+    #[arg(long, global = true, value_enum, default_value_t = TuiTheme::Default)]
+    pub theme: TuiTheme,
+
+    /// Product ID (e.g., 00490-92005-99454-AT527), or `-` to read it from stdin.
+    /// Without the flag, the LYSSA_PID environment variable (which may also be `-`) is used;
+    /// stdin is only read for `-`, never as a fallback
     #[arg(long)]
     pub pid: Option<String>,
 
+    /// File with one Product ID per line - generates keys for each (blank lines and # comments are skipped)
+    #[arg(long, conflicts_with_all = ["spk", "show_spkid", "explain"])]
+    pub pid_file: Option<String>,
+
     /// Existing License Server ID (SPK) - skip SPK generation and only generate LKP
-    #[arg(long)]
+    #[arg(long, requires_all = ["count", "license"])]
     pub spk: Option<String>,
 
+    /// Explain which check a provided --spk fails instead of only reporting a mismatch
+    #[arg(long, requires = "spk")]
+    pub diff: bool,
+
+    /// Only generate the LKP for --pid, without generating or validating any SPK
+    #[arg(long, requires_all = ["count", "license"], conflicts_with_all = ["spk", "export"])]
+    pub lkp_only: bool,
+
     /// License count (1-9999) - generates LKP when provided with --license
-    #[arg(long)]
+    #[arg(long, env = "LYSSA_COUNT", requires = "license")]
     pub count: Option<u32>,
 
-    /// License version and type (e.g., 029_10_2) - generates LKP when provided with --count
-    #[arg(long)]
+    /// License version and type, as a code (e.g., 029_10_2) or part of its description
+    /// (e.g., "2022 Per Device") - generates LKP when provided with --count
+    #[arg(long, env = "LYSSA_LICENSE", requires = "count")]
     pub license: Option<String>,
 
-    /// List all supported license types
+    /// Accept --license codes missing from --list (any CHID_MAJOR_MINOR)
+    #[arg(long, requires = "license")]
+    pub allow_unknown_license: bool,
+
+    /// List all supported license types (as a JSON array with --json)
     #[arg(long)]
     pub list: bool,
+
+    /// Print the version, enabled features, git commit, curve sizes and key alphabet of this
+    /// build (as JSON with --json)
+    #[arg(long)]
+    pub build_info: bool,
+
+    /// Print the same build metadata as --build-info, always as JSON
+    #[arg(long)]
+    pub version_info: bool,
+
+    /// Print the SPKID extracted from --pid without generating any key
+    #[arg(long)]
+    pub show_spkid: bool,
+
+    /// Print every intermediate value of one reproducible (fixed-seed) SPK generation for --pid
+    #[arg(long, conflicts_with_all = ["show_spkid", "json", "quiet", "deterministic"])]
+    pub explain: bool,
+
+    /// Also print the raw signature values `s` and `h` (decimal and hex) of each key, for
+    /// comparing against other implementations
+    #[arg(long, conflicts_with_all = ["pid_file", "quiet"])]
+    pub show_signature: bool,
+
+    /// Generate N distinct SPKs for --pid instead of one SPK and LKP (a JSON array with --json)
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with_all = ["spk", "count", "lkp_only", "pid_file", "show_spkid", "explain", "show_signature", "deterministic", "output", "csv", "export"]
+    )]
+    pub repeat: Option<NonZeroUsize>,
+
+    /// Print results as a single JSON object instead of human-readable text
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// Output format; `--format json` is the same as --json
+    #[arg(long, global = true, value_enum, conflicts_with = "json")]
+    pub format: Option<OutputFormat>,
+
+    /// Print only the raw SPK and LKP, one per line, without any banners
+    #[arg(short, long, global = true, conflicts_with = "json")]
+    pub quiet: bool,
+
+    /// Print results as plain `Key: value` lines without the `=` banners
+    /// (the default when stdout is not a terminal)
+    #[arg(long, global = true, conflicts_with_all = ["json", "quiet"])]
+    pub plain: bool,
+
+    /// Print keys as 35 characters without the dashes between groups
+    #[arg(long, global = true)]
+    pub no_dashes: bool,
+
+    /// Log key generation details to stderr (-v for attempts and rejections, -vv for nonces)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Also write generated keys to this file (JSON with --json, plain text otherwise).
+    /// With --pid-file, a `{pid}` placeholder writes one file per PID
+    #[arg(long)]
+    pub output: Option<String>,
+
+    /// Also write one CSV row per PID (pid,spk,lkp,count,license_code,description,status) to this file
+    #[arg(long)]
+    pub csv: Option<String>,
+
+    /// Also write an offline license package (binary, see `export` module docs) to this file
+    #[arg(long, conflicts_with = "pid_file")]
+    pub export: Option<String>,
+
+    /// Print and validate the keys stored in a license package written by --export
+    #[arg(long)]
+    pub import: Option<String>,
+
+    /// Validate every key in a CSV file with `pid,spk[,lkp]` rows, or `pid,key,kind` rows where
+    /// kind is `spk` or `lkp`. A first row starting with `pid` is a header naming the columns,
+    /// in any order; without one each row is read in one of those two orders
+    #[arg(long)]
+    pub validate_file: Option<String>,
+
+    /// Overwrite existing files written by --output, --csv or --export
+    #[arg(long, requires = "outputs")]
+    pub force: bool,
+
+    /// TOML file with custom [spk] and/or [lkp] curve parameters to sign with instead of the built-in curves
+    #[arg(long, global = true, env = "LYSSA_CURVE_FILE")]
+    pub curve_file: Option<String>,
+
+    /// Signing attempts per key before giving up (each succeeds with roughly 1 in 20 odds)
+    #[arg(long, global = true, env = "LYSSA_MAX_ATTEMPTS", default_value_t = NonZeroUsize::new(DEFAULT_MAX_ATTEMPTS).unwrap())]
+    pub max_attempts: NonZeroUsize,
+
+    /// Derive nonces from the private key and key data (RFC 6979 with HMAC-SHA1) so the
+    /// same input always gives the same keys
+    #[arg(long, global = true)]
+    pub deterministic: bool,
+
+    /// Give up on a key after this many seconds (fractions allowed), also for --serve
+    #[arg(long, global = true, env = "LYSSA_TIMEOUT", value_name = "SECONDS", value_parser = parse_timeout)]
+    pub timeout: Option<Duration>,
+
+    /// Serve an HTTP JSON API on this address (e.g., 127.0.0.1:8080) instead of generating keys
+    #[arg(long)]
+    pub serve: Option<String>,
+
+    /// Serve newline-delimited JSON requests on this Unix domain socket instead of generating keys.
+    /// On Windows this names a pipe, e.g. \\.\pipe\lyssa. A socket file left by a crashed
+    /// server is replaced
+    #[arg(long, value_name = "PATH")]
+    pub socket: Option<String>,
+
+    /// Check the curve points, then run the generate/validate/decode/re-encode suite
+    /// for a sample PID and N random ones (default 5); exits nonzero on any failure
+    #[arg(long, visible_alias = "self-test", value_name = "N", num_args = 0..=1, default_missing_value = "5")]
+    pub selftest: Option<usize>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Subcommands, each equivalent to a combination of the top-level flags
+#[derive(clap::Subcommand)]
+pub enum Command {
+    /// Generate an SPK for a PID (same as --pid)
+    GenSpk {
+        /// Product ID, or `-` to read it from stdin (falls back to LYSSA_PID)
+        #[arg(long)]
+        pid: Option<String>,
+    },
+    /// Generate an LKP for a PID (same as --pid --lkp-only, or --pid --spk to check an existing SPK first)
+    GenLkp {
+        /// Product ID, or `-` to read it from stdin (falls back to LYSSA_PID)
+        #[arg(long)]
+        pid: Option<String>,
+        /// License count (1-9999)
+        #[arg(long, env = "LYSSA_COUNT")]
+        count: u32,
+        /// License version and type, as a code or part of its description
+        #[arg(long, env = "LYSSA_LICENSE")]
+        license: String,
+        /// Existing SPK to validate and print alongside the LKP
+        #[arg(long)]
+        spk: Option<String>,
+    },
+    /// Validate an SPK or LKP against a PID, exiting nonzero if it is invalid
+    #[command(group(clap::ArgGroup::new("key").required(true).args(["spk", "lkp"])))]
+    Validate {
+        /// Product ID, or `-` to read it from stdin (falls back to LYSSA_PID)
+        #[arg(long)]
+        pid: Option<String>,
+        #[arg(long)]
+        spk: Option<String>,
+        #[arg(long)]
+        lkp: Option<String>,
+    },
+    /// Decode the license count, CHID and version carried by an LKP (the signature is not checked)
+    #[command(group(clap::ArgGroup::new("source").required(true).args(["lkp", "package"])))]
+    DecodeLkp {
+        /// Product ID, or `-` to read it from stdin (falls back to LYSSA_PID)
+        #[arg(long, conflicts_with = "package")]
+        pid: Option<String>,
+        #[arg(long)]
+        lkp: Option<String>,
+        /// License package written by --export, supplying both the PID and the LKP
+        #[arg(long)]
+        package: Option<String>,
+    },
+    /// List all supported license types (same as --list)
+    List,
+    /// Launch the graphical interface (same as --gui)
+    Gui,
+    /// Launch the terminal interface (same as --tui)
+    Tui,
+}
+
+/// Formats accepted by --format
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// Color themes offered by the TUI
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum TuiTheme {
+    #[default]
+    Default,
+    Mono,
+    HighContrast,
+}
+
+fn parse_timeout(value: &str) -> Result<Duration, String> {
+    let seconds: f64 = value.parse().map_err(|e| format!("{}", e))?;
+    if !seconds.is_finite() || seconds <= 0.0 {
+        return Err("timeout must be a positive number of seconds".to_string());
+    }
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+/// Curves used to sign and validate SPKs and LKPs
+struct Curves {
+    spk: CurveParams,
+    lkp: CurveParams,
+}
+
+impl Curves {
+    /// Built-in curves, overridden by any table present in `curve_file`
+    fn load(curve_file: Option<&str>) -> anyhow::Result<Self> {
+        let custom = curve_file.map(CurveFile::load).transpose()?;
+        let (spk, lkp) = custom.map_or((None, None), |file| (file.spk, file.lkp));
+
+        Ok(Self {
+            spk: spk.unwrap_or_else(SPKCurve::params),
+            lkp: lkp.unwrap_or_else(LKPCurve::params),
+        })
+    }
+
+    fn for_kind(&self, kind: KeyKind) -> &CurveParams {
+        match kind {
+            KeyKind::Spk => &self.spk,
+            KeyKind::Lkp => &self.lkp,
+        }
+    }
+}
+
+/// Keys generated for a single PID, as printed by `--json` and answered by the server
+#[derive(Serialize)]
+pub struct KeyOutput {
+    pub pid: String,
+    /// Only derived when an SPK is present, since LKPs do not depend on it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spkid: Option<u64>,
+    /// `None` with --lkp-only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spk: Option<String>,
+    #[serde(flatten)]
+    pub lkp: Option<LkpOutput>,
+}
+
+#[derive(Serialize)]
+pub struct LkpOutput {
+    pub license: String,
+    pub description: String,
+    pub count: u32,
+    pub lkp: String,
+}
+
+/// Result of checking one key, as printed by `validate --json` and answered by the server
+#[derive(Serialize)]
+pub struct ValidationOutput {
+    pub pid: String,
+    /// `spk` or `lkp`
+    pub kind: String,
+    pub valid: bool,
+    /// Why the key was rejected, `null` when it is valid
+    pub reason: Option<String>,
+}
+
+impl ValidationOutput {
+    pub fn new(pid: &str, kind: KeyKind, diagnosis: &KeyDiagnosis) -> Self {
+        let valid = *diagnosis == KeyDiagnosis::Valid;
+        Self {
+            pid: pid.to_string(),
+            kind: kind.name().to_lowercase(),
+            valid,
+            reason: (!valid).then(|| diagnosis.to_string()),
+        }
+    }
+}
+
+/// One row of `--csv` output; `status` is `ok` or the error message
+#[derive(Serialize)]
+struct CsvRow {
+    pid: String,
+    spk: String,
+    lkp: String,
+    count: Option<u32>,
+    license_code: String,
+    description: String,
+    status: String,
+}
+
+impl CsvRow {
+    fn new(pid: &str, result: &anyhow::Result<KeyOutput>) -> Self {
+        match result {
+            Ok(output) => {
+                let lkp = output.lkp.as_ref();
+                Self {
+                    pid: pid.to_string(),
+                    spk: output.spk.clone().unwrap_or_default(),
+                    lkp: lkp.map(|l| l.lkp.clone()).unwrap_or_default(),
+                    count: lkp.map(|l| l.count),
+                    license_code: lkp.map(|l| l.license.clone()).unwrap_or_default(),
+                    description: lkp.map(|l| l.description.clone()).unwrap_or_default(),
+                    status: "ok".to_string(),
+                }
+            }
+            Err(e) => Self {
+                pid: pid.to_string(),
+                spk: String::new(),
+                lkp: String::new(),
+                count: None,
+                license_code: String::new(),
+                description: String::new(),
+                status: e.to_string(),
+            },
+        }
+    }
+}
+
+impl KeyOutput {
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!(self)
+    }
+
+    fn to_package(&self) -> LicensePackage {
+        let lkp = self.lkp.as_ref().map(|lkp| PackagedLkp {
+            license_code: lkp.license.clone(),
+            count: lkp.count,
+            lkp: lkp.lkp.clone(),
+        });
+        // --export conflicts with --lkp-only, so an SPK is always present here
+        LicensePackage::new(&self.pid, self.spk.as_deref().unwrap_or_default(), lkp)
+    }
+
+    /// `("spk", key)` and `("lkp", key)` for each key that was generated
+    fn named_keys(&self) -> Vec<(&'static str, &str)> {
+        let spk = self.spk.as_deref().map(|spk| ("spk", spk));
+        let lkp = self.lkp.as_ref().map(|lkp| ("lkp", lkp.lkp.as_str()));
+        spk.into_iter().chain(lkp).collect()
+    }
+
+    /// Raw key strings only: the SPK, then the LKP, each if one was generated
+    fn to_keys(&self) -> String {
+        let mut text = String::new();
+        if let Some(spk) = &self.spk {
+            text.push_str(&format!("{}\n", spk));
+        }
+        if let Some(lkp) = &self.lkp {
+            text.push_str(&format!("{}\n", lkp.lkp));
+        }
+        text
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = format!("PID: {}\n", self.pid);
+        if let Some(spk) = &self.spk {
+            text.push_str(&format!("SPK: {}\n", spk));
+        }
+        if let Some(lkp) = &self.lkp {
+            text.push_str(&format!(
+                "License: {} ({})\nCount: {}\nLKP: {}\n",
+                lkp.license, lkp.description, lkp.count, lkp.lkp
+            ));
+        }
+        text
+    }
+}
+
+/// Subcommands that act on an existing key for the resolved PID instead of generating keys
+#[derive(Debug, PartialEq)]
+enum KeyCommand {
+    Validate(KeyKind, String),
+    DecodeLkp(String),
+    /// `decode-lkp --package`, which takes its PID from the package
+    DecodePackage(String),
+}
+
+impl Cli {
+    /// Whether human-readable output should be plain lines rather than banners
+    fn plain_output(&self) -> bool {
+        self.plain || !std::io::stdout().is_terminal()
+    }
+
+    /// Move a subcommand's arguments, and --format, into the equivalent flags
+    ///
+    /// Returns the key to act on for `validate` and `decode-lkp`, which have no flag equivalent.
+    fn apply_command(&mut self) -> anyhow::Result<Option<KeyCommand>> {
+        if self.format == Some(OutputFormat::Json) {
+            if self.quiet || self.plain {
+                anyhow::bail!("--format json cannot be used with --quiet or --plain");
+            }
+            self.json = true;
+        }
+
+        match self.command.take() {
+            None => {}
+            Some(Command::GenSpk { pid }) => self.pid = pid,
+            Some(Command::GenLkp { pid, count, license, spk }) => {
+                self.pid = pid;
+                self.count = Some(count);
+                self.license = Some(license);
+                self.lkp_only = spk.is_none();
+                self.spk = spk;
+            }
+            Some(Command::Validate { pid, spk, lkp }) => {
+                self.pid = pid;
+                return Ok(match (spk, lkp) {
+                    (Some(spk), _) => Some(KeyCommand::Validate(KeyKind::Spk, spk)),
+                    (None, lkp) => lkp.map(|lkp| KeyCommand::Validate(KeyKind::Lkp, lkp)),
+                });
+            }
+            Some(Command::DecodeLkp { pid, lkp, package }) => {
+                self.pid = pid;
+                return Ok(match (lkp, package) {
+                    (_, Some(path)) => Some(KeyCommand::DecodePackage(path)),
+                    (lkp, None) => lkp.map(KeyCommand::DecodeLkp),
+                });
+            }
+            Some(Command::List) => self.list = true,
+            // main() launches these when they are the only argument
+            Some(Command::Gui | Command::Tui) => {
+                anyhow::bail!("The gui and tui subcommands take no other arguments")
+            }
+        }
+        Ok(None)
+    }
 }
 
 pub fn run_cli() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    let key_command = cli.apply_command()?;
+
+    if cli.verbose > 0 {
+        init_logging(cli.verbose);
+    }
 
     // Handle --list flag
     if cli.list {
-        list_licenses();
-        return Ok(());
+        return list_licenses(cli.json);
+    }
+
+    if cli.build_info || cli.version_info {
+        return print_build_info(cli.json || cli.version_info);
+    }
+
+    // Handle --serve mode
+    if let Some(addr) = &cli.serve {
+        #[cfg(feature = "server")]
+        return crate::server::run_server(addr, cli.timeout);
+
+        #[cfg(not(feature = "server"))]
+        anyhow::bail!("Cannot serve on {}: server feature not enabled. Rebuild with --features server", addr);
+    }
+
+    // Handle --socket mode
+    if let Some(path) = &cli.socket {
+        #[cfg(feature = "server")]
+        return crate::server::run_socket(path, cli.timeout);
+
+        #[cfg(not(feature = "server"))]
+        anyhow::bail!("Cannot listen on {}: server feature not enabled. Rebuild with --features server", path);
+    }
+
+    // Handle --selftest flag
+    if let Some(rounds) = cli.selftest {
+        return run_selftest(rounds);
+    }
+
+    // Refuse to clobber an existing output file before doing any work
+    let output_path = cli.output.as_deref().filter(|p| !p.contains("{pid}"));
+    for path in output_path.into_iter().chain(cli.csv.as_deref()).chain(cli.export.as_deref()) {
+        if Path::new(path).exists() && !cli.force {
+            anyhow::bail!("{} already exists (use --force to overwrite)", path);
+        }
+    }
+
+    let curves = Curves::load(cli.curve_file.as_deref())?;
+
+    // Handle --import of a license package
+    if let Some(path) = &cli.import {
+        return import_package(path, &curves, cli.json);
+    }
+    if let Some(KeyCommand::DecodePackage(path)) = &key_command {
+        return decode_package(path, cli.json);
+    }
+
+    // Handle --validate-file batch validation
+    if let Some(path) = &cli.validate_file {
+        return validate_file(path, &curves, cli.json);
+    }
+
+    // Handle --pid-file batch mode
+    if let Some(pid_file) = &cli.pid_file {
+        return run_batch(&cli, &curves, pid_file);
     }
 
     // Require PID for key generation
-    let pid = cli.pid.as_ref().ok_or_else(|| {
-        anyhow::anyhow!("--pid is required for key generation. Use --help for more information.")
-    })?;
+    let pid = resolve_pid(cli.pid.as_deref(), std::env::var("LYSSA_PID").ok(), std::io::stdin().lock())?;
+    let pid = &normalize_pid(&pid);
+
+    match &key_command {
+        Some(KeyCommand::Validate(kind, key)) => return validate_key(&cli, &curves, pid, *kind, key),
+        Some(KeyCommand::DecodeLkp(lkp)) => return decode_lkp(pid, lkp, cli.json),
+        Some(KeyCommand::DecodePackage(_)) | None => {}
+    }
+
+    if let Err(e) = validate_pid(pid) {
+        eprintln!("Warning: {}", e);
+    }
+
+    // Handle --explain flag
+    if cli.explain {
+        return explain_spk(pid, &curves.spk, cli.max_attempts.get());
+    }
+
+    // Handle --show-spkid flag
+    if cli.show_spkid {
+        show_spkid(pid, cli.json, cli.quiet)?;
+        return Ok(());
+    }
+
+    if let Some(count) = cli.repeat {
+        return print_spk_many(&cli, &curves, pid, count.get());
+    }
+
+    let result = generate_keys(&cli, &curves, pid, !cli.json && !cli.quiet && !cli.plain_output());
+    if let Some(path) = &cli.csv {
+        write_csv(path, &[CsvRow::new(pid, &result)], cli.force)?;
+    }
+    let output = result?;
+
+    if cli.json {
+        let mut value = output.to_json();
+        if cli.show_signature {
+            for (name, key) in output.named_keys() {
+                let (s, h) = signature_components(pid, key)?;
+                value[format!("{}_signature", name)] = serde_json::json!({
+                    "s": s.to_string(),
+                    "s_hex": format!("{:x}", s),
+                    "h": h.to_string(),
+                    "h_hex": format!("{:x}", h),
+                });
+            }
+        }
+        println!("{}", serde_json::to_string_pretty(&value)?);
+    } else if cli.quiet {
+        print!("{}", output.to_keys());
+    } else if cli.plain_output() {
+        print!("{}", output.to_text());
+        if cli.show_signature {
+            for (name, key) in output.named_keys() {
+                let (s, h) = signature_components(pid, key)?;
+                println!("{} signature s: {}", name.to_uppercase(), s);
+                println!("{} signature h: {}", name.to_uppercase(), h);
+            }
+        }
+    } else {
+        println!();
+        if cli.show_signature {
+            for (name, key) in output.named_keys() {
+                let (s, h) = signature_components(pid, key)?;
+                println!("{} signature:", name.to_uppercase());
+                println!("  s = {} (0x{:x})", s, s);
+                println!("  h = {} (0x{:x})\n", h, h);
+            }
+        }
+    }
+
+    if let Some(path) = &cli.output {
+        write_output(&cli, path, std::slice::from_ref(&output), false)?;
+    }
+
+    if let Some(path) = &cli.export {
+        write_file(path, output.to_package().to_bytes()?, cli.force)?;
+    }
+
+    Ok(())
+}
+
+/// Check one key against `pid`, for the `validate` subcommand
+fn validate_key(cli: &Cli, curves: &Curves, pid: &str, kind: KeyKind, key: &str) -> anyhow::Result<()> {
+    let diagnosis = diagnose_tskey(pid, key, curves.for_kind(kind), kind);
+    let valid = diagnosis == KeyDiagnosis::Valid;
+
+    if cli.json {
+        println!("{}", serde_json::to_string_pretty(&ValidationOutput::new(pid, kind, &diagnosis))?);
+    } else if valid && !cli.quiet {
+        println!("{} is valid for {}", kind.name(), pid);
+    }
+
+    if !valid {
+        anyhow::bail!("{} does not match the PID: {}", kind.name(), diagnosis);
+    }
+    Ok(())
+}
+
+/// Print the license fields carried by `lkp`, for the `decode-lkp` subcommand
+fn decode_lkp(pid: &str, lkp: &str, json: bool) -> anyhow::Result<()> {
+    let info = inspect_lkp(pid, lkp)?;
+    let code = license_code(info.chid, info.major_ver, info.minor_ver);
+
+    if json {
+        let output = serde_json::json!({
+            "pid": pid,
+            "chid": info.chid,
+            "count": info.count,
+            "major_ver": info.major_ver,
+            "minor_ver": info.minor_ver,
+            "version_raw": info.version_raw,
+            "license": info.description.is_some().then_some(&code),
+            "description": info.description,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        println!("PID:     {}", pid);
+        println!("CHID:    {}", info.chid);
+        println!("Count:   {}", info.count);
+        println!("Version: {}.{} (raw {})", info.major_ver, info.minor_ver, info.version_raw);
+        match &info.description {
+            Some(description) => println!("License: {} ({})", code, description),
+            None => println!("License: Unknown license type"),
+        }
+    }
+
+    Ok(())
+}
+
+/// `decode-lkp --package`: decode the LKP stored in a license package for its PID
+fn decode_package(path: &str, json: bool) -> anyhow::Result<()> {
+    let package = read_package(path)?;
+    let lkp = package.lkp.ok_or_else(|| anyhow::anyhow!("{} holds no LKP", path))?;
+    decode_lkp(&package.pid, &lkp.lkp, json)
+}
+
+/// Generate `count` distinct SPKs for `pid` and print them, for `--repeat`
+fn print_spk_many(cli: &Cli, curves: &Curves, pid: &str, count: usize) -> anyhow::Result<()> {
+    let spks = generate_spk_many_with_curve(pid, count, &curves.spk, cli.max_attempts.get(), cli.timeout)?
+        .iter()
+        .map(|spk| format_key(cli, spk))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    if cli.json {
+        println!("{}", serde_json::to_string_pretty(&spks)?);
+    } else if cli.quiet {
+        for spk in &spks {
+            println!("{}", spk);
+        }
+    } else {
+        println!("{} SPKs for {}:", spks.len(), pid);
+        for (idx, spk) in spks.iter().enumerate() {
+            println!("{:>3}. {}", idx + 1, spk);
+        }
+    }
+    Ok(())
+}
+
+/// Pick the PID from `--pid`, falling back to LYSSA_PID; `-` from either reads `stdin`
+fn resolve_pid(flag: Option<&str>, env: Option<String>, stdin: impl BufRead) -> anyhow::Result<String> {
+    match flag.map(str::to_string).or(env.filter(|pid| !pid.is_empty())) {
+        Some(pid) if pid == "-" => read_stdin_pid(stdin),
+        Some(pid) => Ok(pid),
+        None => anyhow::bail!("--pid or LYSSA_PID is required for key generation. Use --help for more information."),
+    }
+}
+
+/// Read a single PID from the first line of stdin, for `--pid -`
+fn read_stdin_pid(mut stdin: impl BufRead) -> anyhow::Result<String> {
+    let mut line = String::new();
+    stdin
+        .read_line(&mut line)
+        .map_err(|e| anyhow::anyhow!("Failed to read PID from stdin: {}", e))?;
+    let pid = line.trim();
+    if pid.is_empty() {
+        anyhow::bail!("No PID on stdin (got EOF or an empty line)");
+    }
+    Ok(pid.to_string())
+}
+
+/// Generate keys for every PID listed in `pid_file`
+fn run_batch(cli: &Cli, curves: &Curves, pid_file: &str) -> anyhow::Result<()> {
+    let contents = fs::read_to_string(pid_file)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", pid_file, e))?;
+
+    let pids: Vec<String> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(normalize_pid)
+        .collect();
+
+    if pids.is_empty() {
+        anyhow::bail!("No PIDs found in {}", pid_file);
+    }
+
+    // Progress goes to stderr and only when a human is watching the output
+    let progress = (!cli.json && !cli.quiet && std::io::stdout().is_terminal()).then(|| {
+        let bar = ProgressBar::new(pids.len() as u64);
+        bar.set_style(
+            ProgressStyle::with_template("{bar:40.cyan/blue} {pos}/{len} PIDs [{elapsed_precise}, ETA {eta}] {msg}")
+                .expect("valid progress template")
+                .progress_chars("=> "),
+        );
+        bar
+    });
+
+    let mut outputs = Vec::new();
+    let mut csv_rows = Vec::new();
+    let mut failed = 0;
+
+    for pid in &pids {
+        if let Some(bar) = &progress {
+            bar.set_message(pid.clone());
+        }
+
+        // Per-PID banners would tear the progress bar, so print a summary per PID instead
+        let result = generate_keys(cli, curves, pid, !cli.json && !cli.quiet && !cli.plain_output() && progress.is_none());
+        if cli.csv.is_some() {
+            csv_rows.push(CsvRow::new(pid, &result));
+        }
+
+        match result {
+            Ok(output) => {
+                if let Some(bar) = &progress {
+                    bar.println(output.to_text());
+                } else if cli.quiet {
+                    print!("{}", output.to_keys());
+                } else if !cli.json && cli.plain_output() {
+                    print!("{}", output.to_text());
+                }
+                outputs.push(output);
+            }
+            Err(e) => {
+                match &progress {
+                    Some(bar) => bar.suspend(|| eprintln!("Error for PID {}: {}\n", pid, e)),
+                    None => eprintln!("Error for PID {}: {}", pid, e),
+                }
+                failed += 1;
+            }
+        }
+
+        match &progress {
+            Some(bar) => bar.inc(1),
+            None if !cli.json && !cli.quiet => println!(),
+            None => {}
+        }
+    }
 
-    // Validate --spk parameter requirements
-    if cli.spk.is_some() && (cli.count.is_none() || cli.license.is_none()) {
-        anyhow::bail!("When using --spk, both --count and --license must be provided");
+    if let Some(bar) = &progress {
+        bar.finish_and_clear();
     }
 
-    // Validate LKP parameters if either is provided
-    if (cli.count.is_none()) != (cli.license.is_none()) {
-        anyhow::bail!("Both --count and --license must be provided together for LKP generation");
+    if cli.json {
+        let values: Vec<serde_json::Value> = outputs.iter().map(KeyOutput::to_json).collect();
+        println!("{}", serde_json::to_string_pretty(&values)?);
+    } else if !cli.quiet {
+        println!("Generated keys for {} of {} PIDs", outputs.len(), pids.len());
     }
 
-    println!("Generating keys for PID: {}\n", pid);
+    if let Some(path) = &cli.output {
+        write_output(cli, path, &outputs, true)?;
+    }
+
+    if let Some(path) = &cli.csv {
+        write_csv(path, &csv_rows, cli.force)?;
+    }
 
-    // Handle SPK - either validate existing or generate new
-    let _spk = if let Some(existing_spk) = &cli.spk {
-        println!("{}", "=".repeat(60));
-        println!("Validating provided SPK: {}", existing_spk);
+    if failed > 0 {
+        anyhow::bail!("{} of {} PIDs failed", failed, pids.len());
+    }
+
+    Ok(())
+}
+
+/// Validate or generate the SPK for `pid` (unless --lkp-only), then generate the LKP if requested
+///
+/// Progress banners are printed to stdout only when `human` is set.
+fn generate_keys(cli: &Cli, curves: &Curves, pid: &str, human: bool) -> anyhow::Result<KeyOutput> {
+    if human {
+        println!("Generating keys for PID: {}\n", pid);
+    }
+
+    // Handle SPK - either skip it, validate existing or generate new
+    let spk = if cli.lkp_only {
+        None
+    } else if let Some(existing_spk) = &cli.spk {
+        if human {
+            println!("{}", "=".repeat(60));
+            println!("Validating provided SPK: {}", existing_spk);
+        }
         
-        let is_valid = validate_tskey(
-            pid,
-            existing_spk,
-            SPKCurve::gx(),
-            SPKCurve::gy(),
-            SPKCurve::kx(),
-            SPKCurve::ky(),
-            num_bigint::BigUint::from(SPKCurve::A),
-            SPKCurve::p(),
-            true,
-        )?;
+        let is_valid = validate_tskey(pid, existing_spk, &curves.spk, KeyKind::Spk)?;
         
         if !is_valid {
-            println!("{}", "=".repeat(60));
+            if human {
+                println!("{}", "=".repeat(60));
+            }
+            if cli.diff {
+                anyhow::bail!(
+                    "Provided SPK does not match the PID: {}",
+                    diagnose_tskey(pid, existing_spk, &curves.spk, KeyKind::Spk)
+                );
+            }
             anyhow::bail!("Provided SPK does not match the PID");
         }
         
-        println!("SPK validation successful!");
-        println!("{}", "=".repeat(60));
-        existing_spk.clone()
+        if human {
+            println!("SPK validation successful!");
+            println!("{}", "=".repeat(60));
+        }
+        Some(format_key(cli, existing_spk)?)
     } else {
-        println!("{}", "=".repeat(60));
-        let spk = generate_spk(pid)?;
-        println!("License Server ID (SPK):\n{}", spk);
-        println!("{}", "=".repeat(60));
-        spk
+        if human {
+            println!("{}", "=".repeat(60));
+        }
+        let generate = if cli.deterministic {
+            generate_spk_rfc6979
+        } else {
+            generate_spk_with_curve
+        };
+        let spk = format_key(cli, &generate(pid, &curves.spk, cli.max_attempts.get(), cli.timeout)?)?;
+        if human {
+            println!("License Server ID (SPK):\n{}", spk);
+            println!("{}", "=".repeat(60));
+        }
+        Some(spk)
+    };
+
+    let mut output = KeyOutput {
+        pid: pid.to_string(),
+        spkid: spk.as_ref().map(|_| derive_spkid(pid)).transpose()?.map(|d| d.spkid),
+        spk,
+        lkp: None,
     };
 
     // Generate LKP if parameters provided
-    if let (Some(count), Some(license_type)) = (cli.count, cli.license.as_ref()) {
-        let license_info = LicenseInfo::parse(license_type)?;
+    if let (Some(count), Some(license)) = (cli.count, cli.license.as_deref()) {
+        let (license_type, license_info) = match LicenseInfo::parse_raw(license) {
+            Ok(info) if cli.allow_unknown_license => (license, info),
+            _ => {
+                let (code, _) = find_license(license)?;
+                (*code, LicenseInfo::parse(code)?)
+            }
+        };
 
         if !(1..=9999).contains(&count) {
             anyhow::bail!("License count must be between 1 and 9999");
         }
 
-        println!("\nLicense Type: {}", license_info.description);
-        println!("License Count: {}\n", count);
-        println!("{}", "=".repeat(60));
+        if human {
+            println!("\nLicense Type: {}", license_info.description);
+            println!("License Count: {}\n", count);
+            println!("{}", "=".repeat(60));
+        }
         
-        let lkp = generate_lkp(
+        let generate = if cli.deterministic {
+            generate_lkp_rfc6979
+        } else {
+            generate_lkp_with_curve
+        };
+        let lkp = generate(
             pid,
             count,
             license_info.chid,
             license_info.major_ver,
             license_info.minor_ver,
+            &curves.lkp,
+            cli.max_attempts.get(),
+            cli.timeout,
         )?;
+        let lkp = format_key(cli, &lkp)?;
         
-        println!("License Key Pack (LKP):\n{}", lkp);
-        println!("{}", "=".repeat(60));
+        if human {
+            println!("License Key Pack (LKP):\n{}", lkp);
+            println!("{}", "=".repeat(60));
+        }
+
+        output.lkp = Some(LkpOutput {
+            license: license_type.to_string(),
+            description: license_info.description,
+            count,
+            lkp,
+        });
     }
 
-    println!();
+    Ok(output)
+}
+
+/// Re-encode `key` in canonical groups, or without separators when --no-dashes is set
+fn format_key(cli: &Cli, key: &str) -> anyhow::Result<String> {
+    let opts = if cli.no_dashes {
+        EncodeOptions::ungrouped()
+    } else {
+        EncodeOptions::default()
+    };
+    Ok(encode_pkey_with(&decode_pkey(key)?, &opts))
+}
+
+/// Write results to `template`, one file per PID if it contains `{pid}`
+fn write_output(cli: &Cli, template: &str, outputs: &[KeyOutput], batch: bool) -> anyhow::Result<()> {
+    if template.contains("{pid}") {
+        for output in outputs {
+            let path = template.replace("{pid}", &output.pid);
+            write_file(&path, &render_outputs(std::slice::from_ref(output), cli.json, false)?, cli.force)?;
+        }
+    } else {
+        write_file(template, &render_outputs(outputs, cli.json, batch)?, cli.force)?;
+    }
+
+    Ok(())
+}
+
+fn render_outputs(outputs: &[KeyOutput], json: bool, as_list: bool) -> anyhow::Result<String> {
+    if json {
+        let value = if as_list {
+            serde_json::Value::Array(outputs.iter().map(KeyOutput::to_json).collect())
+        } else {
+            outputs[0].to_json()
+        };
+        Ok(serde_json::to_string_pretty(&value)? + "\n")
+    } else {
+        Ok(outputs
+            .iter()
+            .map(KeyOutput::to_text)
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+fn write_file(path: &str, contents: impl AsRef<[u8]>, force: bool) -> anyhow::Result<()> {
+    let path = Path::new(path);
+
+    if path.exists() && !force {
+        anyhow::bail!("{} already exists (use --force to overwrite)", path.display());
+    }
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(path, contents)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+    eprintln!("Saved keys to {}", path.display());
+
+    Ok(())
+}
+
+fn write_csv(path: &str, rows: &[CsvRow], force: bool) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    let contents = String::from_utf8(writer.into_inner()?)?;
+    write_file(path, &contents, force)
+}
+
+/// PID of the first self-test round, so every run covers at least one known input
+const SELFTEST_PID: &str = "00490-92005-99454-AT527";
+
+/// Check the built-in curve points, then generate, validate, decode and re-encode
+/// SPKs and LKPs for the sample PID and `rounds` random ones
+fn run_selftest(rounds: usize) -> anyhow::Result<()> {
+    let mut rng = rand::thread_rng();
+    let mut passed = 0;
+    let mut failed = 0;
+
+    for (name, curve) in [("SPK", SPKCurve::params()), ("LKP", LKPCurve::params())] {
+        match curve.validate() {
+            Ok(()) => {
+                passed += 1;
+                println!("[curve] PASS  {} base points on curve", name);
+            }
+            Err(e) => {
+                failed += 1;
+                println!("[curve] FAIL  {}: {}", name, e);
+            }
+        }
+    }
+
+    let total = rounds + 1;
+    for round in 1..=total {
+        let pid = if round == 1 {
+            SELFTEST_PID.to_string()
+        } else {
+            format!(
+                "{:05}-{:05}-{:05}-{:05}",
+                rng.gen_range(0..100000),
+                rng.gen_range(0..100000),
+                rng.gen_range(0..100000),
+                rng.gen_range(0..100000)
+            )
+        };
+        let (license_type, _) = LICENSE_TYPES[rng.gen_range(0..LICENSE_TYPES.len())];
+        let count = rng.gen_range(1..=9999);
+
+        match selftest_round(&pid, license_type, count) {
+            Ok(()) => {
+                passed += 1;
+                println!("[{}/{}] PASS  {} ({} x{})", round, total, pid, license_type, count);
+            }
+            Err(e) => {
+                failed += 1;
+                println!("[{}/{}] FAIL  {} ({} x{}): {}", round, total, pid, license_type, count, e);
+            }
+        }
+    }
+
+    println!("\nSelf-test: {} passed, {} failed", passed, failed);
+
+    if failed > 0 {
+        anyhow::bail!("Self-test failed");
+    }
+    Ok(())
+}
+
+fn selftest_round(pid: &str, license_type: &str, count: u32) -> anyhow::Result<()> {
+    let license_info = LicenseInfo::parse(license_type)?;
+
+    let spk = generate_spk(pid)?;
+    let spk_valid = validate_tskey(pid, &spk, &SPKCurve::params(), KeyKind::Spk)?;
+    if !spk_valid {
+        anyhow::bail!("generated SPK failed validation");
+    }
+
+    let lkp = generate_lkp(
+        pid,
+        count,
+        license_info.chid,
+        license_info.major_ver,
+        license_info.minor_ver,
+    )?;
+    let lkp_valid = validate_tskey(pid, &lkp, &LKPCurve::params(), KeyKind::Lkp)?;
+    if !lkp_valid {
+        anyhow::bail!("generated LKP failed validation");
+    }
+
+    for (name, key) in [("SPK", &spk), ("LKP", &lkp)] {
+        let reencoded = encode_pkey(&decode_pkey(key)?);
+        if &reencoded != key {
+            anyhow::bail!("{} re-encoded as {} instead of {}", name, reencoded, key);
+        }
+    }
+
+    Ok(())
+}
+
+fn read_package(path: &str) -> anyhow::Result<LicensePackage> {
+    let bytes = fs::read(path).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path, e))?;
+    LicensePackage::from_bytes(&bytes)
+}
+
+/// Print the contents of a license package and check its keys against `curves`
+fn import_package(path: &str, curves: &Curves, json: bool) -> anyhow::Result<()> {
+    let package = read_package(path)?;
+
+    let spk_valid = validate_tskey(&package.pid, &package.spk, &curves.spk, KeyKind::Spk)?;
+    let lkp_valid = package
+        .lkp
+        .as_ref()
+        .map(|lkp| validate_tskey(&package.pid, &lkp.lkp, &curves.lkp, KeyKind::Lkp))
+        .transpose()?;
+
+    if json {
+        let mut value = serde_json::json!({
+            "pid": package.pid,
+            "spk": package.spk,
+            "spk_valid": spk_valid,
+            "timestamp": package.timestamp,
+        });
+        if let Some(lkp) = &package.lkp {
+            value["license"] = serde_json::json!(lkp.license_code);
+            value["count"] = serde_json::json!(lkp.count);
+            value["lkp"] = serde_json::json!(lkp.lkp);
+            value["lkp_valid"] = serde_json::json!(lkp_valid);
+        }
+        println!("{}", serde_json::to_string_pretty(&value)?);
+    } else {
+        let status = |valid: bool| if valid { "valid" } else { "INVALID" };
+        println!("PID:       {}", package.pid);
+        println!("Created:   {} (Unix time)", package.timestamp);
+        println!("SPK:       {} ({})", package.spk, status(spk_valid));
+        if let (Some(lkp), Some(valid)) = (&package.lkp, lkp_valid) {
+            println!("License:   {}", lkp.license_code);
+            println!("Count:     {}", lkp.count);
+            println!("LKP:       {} ({})", lkp.lkp, status(valid));
+        }
+    }
+
+    if !spk_valid || lkp_valid == Some(false) {
+        anyhow::bail!("License package {} contains invalid keys", path);
+    }
     Ok(())
 }
 
-fn list_licenses() {
+/// `(kind label, kind, key)` for one key of a `--validate-file` row
+type RowKey = (String, anyhow::Result<KeyKind>, String);
+
+/// Column positions in a `--validate-file` CSV
+#[derive(Clone, Copy)]
+enum ValidateColumns {
+    /// `pid,key,kind`, where kind is `spk` or `lkp`
+    Kinded { pid: usize, key: usize, kind: usize },
+    /// `pid,spk,lkp`, where either key may be missing or empty
+    Keys { pid: usize, spk: Option<usize>, lkp: Option<usize> },
+}
+
+impl ValidateColumns {
+    /// Columns named by a header row
+    fn from_header(header: &csv::StringRecord) -> anyhow::Result<Self> {
+        let find = |name: &str| header.iter().position(|field| field.trim().eq_ignore_ascii_case(name));
+        let pid = find("pid").ok_or_else(|| anyhow::anyhow!("Header has no pid column"))?;
+        match (find("key"), find("kind"), find("spk"), find("lkp")) {
+            (Some(key), Some(kind), _, _) => Ok(Self::Kinded { pid, key, kind }),
+            (_, _, None, None) => anyhow::bail!("Header needs key and kind columns, or spk and/or lkp columns"),
+            (_, _, spk, lkp) => Ok(Self::Keys { pid, spk, lkp }),
+        }
+    }
+
+    /// Columns of a row without a header: `pid,key,kind` if the third field is a
+    /// key kind (a key never is), otherwise `pid,spk,lkp`
+    fn positional(record: &csv::StringRecord) -> Self {
+        if record.get(2).is_some_and(|field| field.parse::<KeyKind>().is_ok()) {
+            Self::Kinded { pid: 0, key: 1, kind: 2 }
+        } else {
+            Self::Keys { pid: 0, spk: Some(1), lkp: Some(2) }
+        }
+    }
+
+    /// The PID of `record` and each key it lists
+    fn read(self, record: &csv::StringRecord) -> anyhow::Result<(String, Vec<RowKey>)> {
+        let field = |index: Option<usize>| {
+            index
+                .and_then(|index| record.get(index))
+                .map(str::trim)
+                .filter(|field| !field.is_empty())
+        };
+        let (pid, keys) = match self {
+            Self::Kinded { pid, key, kind } => {
+                let key = field(Some(key)).ok_or_else(|| anyhow::anyhow!("Row has no key"))?;
+                let kind = field(Some(kind)).unwrap_or_default();
+                (pid, vec![(kind.to_string(), kind.parse::<KeyKind>(), key.to_string())])
+            }
+            Self::Keys { pid, spk, lkp } => {
+                let keys: Vec<_> = [(KeyKind::Spk, spk), (KeyKind::Lkp, lkp)]
+                    .into_iter()
+                    .filter_map(|(kind, index)| {
+                        field(index).map(|key| (kind.name().to_lowercase(), Ok(kind), key.to_string()))
+                    })
+                    .collect();
+                if keys.is_empty() {
+                    anyhow::bail!("Row has no SPK or LKP");
+                }
+                (pid, keys)
+            }
+        };
+        let pid = field(Some(pid)).ok_or_else(|| anyhow::anyhow!("Row has no PID"))?;
+        Ok((normalize_pid(pid), keys))
+    }
+}
+
+/// Verdict on one `--validate-file` row
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RowStatus {
+    Valid,
+    /// The key decodes but fails a check for the PID, such as its signature or SPKID
+    Invalid,
+    /// The row, its kind or its key could not be parsed at all, including a truncated key
+    Unreadable,
+}
+
+/// Outcome for one key of a `--validate-file` CSV
+struct ValidateResult {
+    /// Line the row starts on
+    line: u64,
+    pid: String,
+    kind: String,
+    status: RowStatus,
+    error: Option<String>,
+}
+
+/// Check every key of a `pid,spk,lkp` or `pid,key,kind` CSV
+///
+/// A first row starting with `pid` is taken as a header naming the columns;
+/// otherwise the layout of each row is worked out by [`ValidateColumns::positional`].
+fn check_validate_file(path: &str, curves: &Curves) -> anyhow::Result<Vec<ValidateResult>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path, e))?;
+    let mut records = reader.records().peekable();
+    let columns = match records.peek() {
+        Some(Ok(first)) if first.get(0).is_some_and(|field| field.trim().eq_ignore_ascii_case("pid")) => {
+            let header = records.next().and_then(Result::ok).unwrap_or_default();
+            Some(ValidateColumns::from_header(&header)?)
+        }
+        _ => None,
+    };
+    let mut results = Vec::new();
+
+    for record in records {
+        // Where the record starts, which differs from its index once a quoted field spans lines
+        let line = match &record {
+            Ok(record) => record.position(),
+            Err(e) => e.position(),
+        }
+        .map_or(0, csv::Position::line);
+        let row = record
+            .map_err(anyhow::Error::from)
+            .and_then(|record| columns.unwrap_or_else(|| ValidateColumns::positional(&record)).read(&record));
+        let (pid, keys) = match row {
+            Ok(row) => row,
+            Err(e) => (String::new(), vec![(String::new(), Err(e), String::new())]),
+        };
+
+        for (kind_label, kind, key) in keys {
+            let diagnosis = kind.map(|kind| diagnose_tskey(&pid, &key, curves.for_kind(kind), kind));
+            let (status, error) = match diagnosis {
+                Ok(KeyDiagnosis::Valid) => (RowStatus::Valid, None),
+                Ok(diagnosis @ (KeyDiagnosis::Malformed(_) | KeyDiagnosis::TooShort { .. })) => {
+                    (RowStatus::Unreadable, Some(diagnosis.to_string()))
+                }
+                Ok(diagnosis) => (RowStatus::Invalid, Some(diagnosis.to_string())),
+                Err(e) => (RowStatus::Unreadable, Some(e.to_string())),
+            };
+            results.push(ValidateResult {
+                line,
+                pid: pid.clone(),
+                kind: kind_label,
+                status,
+                error,
+            });
+        }
+    }
+
+    Ok(results)
+}
+
+/// Validate the keys in a `--validate-file` CSV, printing a verdict per row
+fn validate_file(path: &str, curves: &Curves, json: bool) -> anyhow::Result<()> {
+    let results = check_validate_file(path, curves)?;
+    let count = |wanted| results.iter().filter(|result| result.status == wanted).count();
+    let (valid, invalid, unreadable) = (count(RowStatus::Valid), count(RowStatus::Invalid), count(RowStatus::Unreadable));
+
+    if json {
+        let values: Vec<serde_json::Value> = results
+            .iter()
+            .map(|result| {
+                serde_json::json!({
+                    "line": result.line,
+                    "pid": result.pid,
+                    "kind": result.kind,
+                    "valid": result.status == RowStatus::Valid,
+                    "unreadable": result.status == RowStatus::Unreadable,
+                    "error": result.error,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&values)?);
+    } else {
+        for result in &results {
+            let verdict = match result.status {
+                RowStatus::Valid => "OK",
+                RowStatus::Invalid => "FAIL",
+                RowStatus::Unreadable => "ERROR",
+            };
+            print!("Line {}: {} {} {}", result.line, result.pid, result.kind, verdict);
+            match &result.error {
+                Some(error) => println!(" ({})", error),
+                None => println!(),
+            }
+        }
+        println!(
+            "{} of {} keys valid, {} invalid, {} unreadable",
+            valid,
+            results.len(),
+            invalid,
+            unreadable
+        );
+    }
+
+    if valid < results.len() {
+        anyhow::bail!("{} of {} keys are invalid and {} could not be read", invalid, results.len(), unreadable);
+    }
+    Ok(())
+}
+
+/// Seed of the nonce RNG used by --explain, so its output is the same on every run
+const EXPLAIN_SEED: u64 = 0;
+
+/// Generate an SPK with a seeded RNG and print each step of the signing computation
+fn explain_spk(pid: &str, curve: &CurveParams, max_attempts: usize) -> anyhow::Result<()> {
+    let spkid = derive_spkid(pid)?.spkid;
+    let spkdata = bigint_to_bytes_le(&BigUint::from(spkid), 7)?;
+    let mut rng = StdRng::seed_from_u64(EXPLAIN_SEED);
+    let trace = generate_tskey_traced(pid, &spkdata, curve, KeyKind::Spk, max_attempts, &mut rng)?;
+
+    let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    println!("PID:                {}", pid);
+    println!("SPKID:              {}", spkid);
+    println!("Key data (7 B LE):  {}", hex(&spkdata));
+    println!("RC4 key:            {}  (MD5(UTF-16LE PID)[..5] + 11 zero bytes)", hex(&trace.rc4_key));
+    println!("Nonce seed:         {}  (succeeded on attempt {})", EXPLAIN_SEED, trace.attempts);
+    println!("Nonce:              {}", trace.nonce);
+    println!("R = nonce*G:  x =   {}", trace.rx);
+    println!("              y =   {}", trace.ry);
+    println!("SHA-1 input:        {}  (key data + R.x + R.y, 48 B LE each)", hex(&trace.sha1_input));
+    println!("SHA-1 digest:       {}", hex(&trace.sha1_digest));
+    println!("h (35 bits):        {:#x}", trace.h);
+    println!("s (69 bits):        {:#x}  (nonce - priv * h mod n)", trace.s);
+    println!("s << 35 | h:        {:#x}", trace.sigdata);
+    println!("Plaintext (21 B):   {}", hex(&trace.pkdata));
+    println!("RC4 ciphertext:     {}", hex(&trace.encrypted));
+    println!("SPK:                {}", trace.key);
+    Ok(())
+}
+
+fn show_spkid(pid: &str, json: bool, quiet: bool) -> anyhow::Result<()> {
+    let derivation = derive_spkid(pid)?;
+
+    if json {
+        let output = serde_json::json!({
+            "pid": pid,
+            "spkid": derivation.spkid,
+            "pid_part1": derivation.part1,
+            "pid_part2": derivation.part2,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if quiet {
+        println!("{}", derivation.spkid);
+    } else {
+        println!("PID:         {}", pid);
+        println!("PID[10..16]: {}", derivation.part1);
+        println!("PID[18..23]: {}", derivation.part2);
+        println!("SPKID:       {}", derivation.spkid);
+    }
+
+    Ok(())
+}
+
+/// Send `tracing` events to stderr at a level chosen by the number of `-v` flags
+fn init_logging(verbose: u8) {
+    let level = match verbose {
+        1 => tracing::Level::INFO,
+        _ => tracing::Level::DEBUG,
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .init();
+}
+
+/// Build metadata, so wrappers can check e.g. for the server feature before using --serve
+fn print_build_info(json: bool) -> anyhow::Result<()> {
+    let features: Vec<&str> = [
+        ("gui", cfg!(feature = "gui")),
+        ("tui", cfg!(feature = "tui")),
+        ("server", cfg!(feature = "server")),
+        ("wasm", cfg!(feature = "wasm")),
+        ("cffi", cfg!(feature = "cffi")),
+        ("cjk-font", cfg!(feature = "cjk-font")),
+        ("parallel", cfg!(feature = "parallel")),
+        ("serde", cfg!(feature = "serde")),
+        ("qr", cfg!(feature = "qr")),
+    ]
+    .into_iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(name, _)| name)
+    .collect();
+    let version = env!("CARGO_PKG_VERSION");
+    let git_commit = option_env!("LYSSA_GIT_COMMIT");
+    let (spk_n_bits, lkp_n_bits) = (SPKCurve::params().n.bits(), LKPCurve::params().n.bits());
+
+    if json {
+        let info = serde_json::json!({
+            "version": version,
+            "features": features,
+            "license_types": LICENSE_TYPES.len(),
+            "git_commit": git_commit,
+            "spk_curve_n_bits": spk_n_bits,
+            "lkp_curve_n_bits": lkp_n_bits,
+            "key_alphabet": KCHARS,
+        });
+        println!("{}", serde_json::to_string_pretty(&info)?);
+        return Ok(());
+    }
+
+    println!("lyssa_rds_gen {}", version);
+    println!("Features:      {}", if features.is_empty() { "none".to_string() } else { features.join(", ") });
+    println!("License types: {}", LICENSE_TYPES.len());
+    println!("Git commit:    {}", git_commit.unwrap_or("unknown"));
+    println!("SPK curve n:   {} bits", spk_n_bits);
+    println!("LKP curve n:   {} bits", lkp_n_bits);
+    println!("Key alphabet:  {}", KCHARS);
+    Ok(())
+}
+
+fn list_licenses(json: bool) -> anyhow::Result<()> {
+    if json {
+        let catalog = LICENSE_TYPES
+            .iter()
+            .map(|(code, _)| {
+                let info = LicenseInfo::parse(code)?;
+                Ok(serde_json::json!({
+                    "code": code,
+                    "chid": info.chid,
+                    "major_ver": info.major_ver,
+                    "minor_ver": info.minor_ver,
+                    "description": info.description,
+                }))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        println!("{}", serde_json::to_string_pretty(&catalog)?);
+        return Ok(());
+    }
+
     println!("\nSupported License Version and Type:\n");
     for (code, description) in LICENSE_TYPES {
         println!("  {:12} - {}", code, description);
     }
     println!();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("lyssa_rds_gen").chain(args.split_whitespace()))
+    }
+
+    #[test]
+    fn test_flag_combinations() {
+        let pid = "--pid 00490-92005-99454-AT527";
+        for valid in [
+            pid.to_string(),
+            format!("{} --count 5 --license 029_10_2", pid),
+            format!("{} --spk X --count 5 --license 029_10_2", pid),
+            "--list --json".to_string(),
+            "--pid-file pids.txt --count 5 --license 029_10_2".to_string(),
+            "--serve 127.0.0.1:8080".to_string(),
+            "--self-test".to_string(),
+            "--selftest 3".to_string(),
+            format!("{} --repeat 3 --json", pid),
+            format!("{} --plain --show-signature", pid),
+            "--validate-file keys.csv --format json".to_string(),
+            "--version-info".to_string(),
+        ] {
+            assert!(parse(&valid).is_ok(), "{} was rejected", valid);
+        }
+
+        for invalid in [
+            format!("{} --count 5", pid),
+            format!("{} --license 029_10_2", pid),
+            format!("{} --spk X", pid),
+            format!("{} --list", pid),
+            format!("{} --pid-file pids.txt", pid),
+            format!("{} --import package.bin", pid),
+            "--import package.bin --validate-file keys.csv".to_string(),
+            "--serve 127.0.0.1:8080 --socket lyssa.sock".to_string(),
+            format!("{} --self-test", pid),
+            format!("{} --version-info", pid),
+            "--build-info --version-info".to_string(),
+            format!("{} --repeat 0", pid),
+            format!("{} --repeat 3 --count 5 --license 029_10_2", pid),
+            format!("{} --plain --json", pid),
+            "--validate-file keys.csv --format json --json".to_string(),
+        ] {
+            let err = parse(&invalid).err().unwrap_or_else(|| panic!("{} was accepted", invalid));
+            assert_ne!(err.exit_code(), 0, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_resolve_pid() {
+        let env = || Some("00490-12345-67890-AT124".to_string());
+        let stdin = || &b"  00490-92005-99454-AT527 \nignored\n"[..];
+        assert_eq!(resolve_pid(Some("00490-11111-22222-AT333"), env(), stdin()).unwrap(), "00490-11111-22222-AT333");
+        assert_eq!(resolve_pid(None, env(), stdin()).unwrap(), "00490-12345-67890-AT124");
+        assert!(resolve_pid(None, Some(String::new()), stdin()).is_err());
+        assert!(resolve_pid(None, None, stdin()).is_err());
+
+        // `-` reads the first line of stdin, from the flag or the environment
+        assert_eq!(resolve_pid(Some("-"), env(), stdin()).unwrap(), "00490-92005-99454-AT527");
+        assert_eq!(resolve_pid(None, Some("-".to_string()), stdin()).unwrap(), "00490-92005-99454-AT527");
+        assert!(resolve_pid(Some("-"), None, &b"\n"[..]).is_err());
+        assert!(resolve_pid(Some("-"), None, &b""[..]).is_err());
+    }
+
+    #[test]
+    fn test_subcommands() {
+        let pid = "00490-92005-99454-AT527";
+        let mut cli = parse(&format!("--json gen-lkp --pid {} --count 5 --license 029_10_2 --deterministic", pid)).unwrap();
+        assert!(cli.apply_command().unwrap().is_none());
+        assert_eq!(cli.pid.as_deref(), Some(pid));
+        assert_eq!((cli.count, cli.license.as_deref()), (Some(5), Some("029_10_2")));
+        assert!(cli.lkp_only && cli.json && cli.deterministic);
+
+        let mut cli = parse(&format!("validate --pid {} --lkp K --quiet", pid)).unwrap();
+        assert_eq!(cli.apply_command().unwrap(), Some(KeyCommand::Validate(KeyKind::Lkp, "K".to_string())));
+        assert!(cli.quiet);
+
+        let mut cli = parse(&format!("decode-lkp --pid {} --lkp K", pid)).unwrap();
+        assert_eq!(cli.apply_command().unwrap(), Some(KeyCommand::DecodeLkp("K".to_string())));
+        let mut cli = parse("decode-lkp --package package.bin").unwrap();
+        assert_eq!(cli.apply_command().unwrap(), Some(KeyCommand::DecodePackage("package.bin".to_string())));
+
+        let mut cli = parse("--validate-file keys.csv --format json").unwrap();
+        assert!(cli.apply_command().unwrap().is_none() && cli.json);
+        assert!(parse("--validate-file keys.csv --format json -q").unwrap().apply_command().is_err());
+
+        assert!(parse("list").unwrap().apply_command().unwrap().is_none());
+        assert!(parse("gui").unwrap().apply_command().is_err());
+
+        for invalid in [
+            format!("validate --pid {}", pid),
+            format!("validate --pid {} --spk K --lkp K", pid),
+            format!("decode-lkp --pid {}", pid),
+            format!("decode-lkp --pid {} --package package.bin", pid),
+            "decode-lkp --lkp K --package package.bin".to_string(),
+            format!("gen-lkp --pid {} --count 5", pid),
+            format!("gen-spk --pid {} --count 5", pid),
+        ] {
+            assert!(parse(&invalid).is_err(), "{} was accepted", invalid);
+        }
+    }
+
+    #[test]
+    fn test_gen_lkp_without_numeric_spkid() {
+        // The SPKID slice "X" is not a number, which only matters when an SPK is made
+        let pid = "00490-9200X-99454-AT527";
+        let curves = Curves::load(None).unwrap();
+        let mut cli = parse(&format!("gen-lkp --pid {} --count 5 --license 029_10_2", pid)).unwrap();
+        cli.apply_command().unwrap();
+
+        let output = generate_keys(&cli, &curves, pid, false).unwrap();
+        assert_eq!((output.spk, output.spkid), (None, None));
+        let info = inspect_lkp(pid, &output.lkp.unwrap().lkp).unwrap();
+        assert_eq!((info.count, info.chid), (5, 29));
+
+        let cli = parse(&format!("--pid {} --count 5 --license 029_10_2", pid)).unwrap();
+        assert!(generate_keys(&cli, &curves, pid, false).is_err());
+    }
+
+    #[test]
+    fn test_decode_package() {
+        let pid = "00490-92005-99454-AT527";
+        let lkp = PackagedLkp {
+            license_code: "029_10_2".to_string(),
+            count: 5,
+            lkp: generate_lkp(pid, 5, 29, 10, 2).unwrap(),
+        };
+        let path = std::env::temp_dir().join(format!("lyssa_rds_gen_package_{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        fs::write(path, LicensePackage::new(pid, "K", Some(lkp)).to_bytes().unwrap()).unwrap();
+        assert!(decode_package(path, true).is_ok());
+        fs::write(path, LicensePackage::new(pid, "K", None).to_bytes().unwrap()).unwrap();
+        assert!(decode_package(path, true).is_err());
+
+        fs::remove_file(path).unwrap();
+        assert!(decode_package(path, true).is_err());
+    }
+
+    #[test]
+    fn test_validate_file() {
+        let pid = "00490-92005-99454-AT527";
+        let spk = generate_spk(pid).unwrap();
+        let lkp = generate_lkp(pid, 5, 29, 10, 2).unwrap();
+        let path = std::env::temp_dir().join(format!("lyssa_rds_gen_validate_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let curves = Curves::load(None).unwrap();
+
+        fs::write(path, format!("pid,key,kind\n{pid},{spk},spk\n{pid},{lkp},LKP\n")).unwrap();
+        assert!(validate_file(path, &curves, true).is_ok());
+
+        // An SPK checked as an LKP fails, while an unknown kind or a garbled key is unreadable
+        fs::write(path, format!("pid,key,kind\n{pid},{spk},spk\n{pid},{spk},lkp\n{pid},{lkp},xyz\n{pid},ABC,spk\n")).unwrap();
+        let err = validate_file(path, &curves, true).unwrap_err();
+        assert_eq!(err.to_string(), "1 of 4 keys are invalid and 2 could not be read");
+
+        // Each failing row names the check that rejected it
+        fs::write(path, format!("{pid},{lkp},spk\n{pid},BCDFG,spk\n")).unwrap();
+        let errors: Vec<(RowStatus, String)> = check_validate_file(path, &curves)
+            .unwrap()
+            .into_iter()
+            .map(|result| (result.status, result.error.unwrap()))
+            .collect();
+        assert_eq!(errors[0].0, RowStatus::Invalid);
+        assert!(errors[0].1.starts_with("signature hash mismatch"), "{}", errors[0].1);
+        assert_eq!(errors[1], (RowStatus::Unreadable, "key is too short (5 characters, expected 35)".to_string()));
+
+        // Headerless `pid,spk[,lkp]` rows, and a header naming the columns in another order
+        fs::write(path, format!("{pid},{spk}\n{pid},{spk},{lkp}\n{pid},,{spk}\n{pid}\n")).unwrap();
+        let statuses: Vec<(u64, String, RowStatus)> = check_validate_file(path, &curves)
+            .unwrap()
+            .into_iter()
+            .map(|result| (result.line, result.kind, result.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                (1, "spk".to_string(), RowStatus::Valid),
+                (2, "spk".to_string(), RowStatus::Valid),
+                (2, "lkp".to_string(), RowStatus::Valid),
+                (3, "lkp".to_string(), RowStatus::Invalid),
+                (4, String::new(), RowStatus::Unreadable),
+            ]
+        );
+        fs::write(path, format!("lkp,PID\n{lkp},{pid}\n")).unwrap();
+        assert!(validate_file(path, &curves, false).is_err());
+        fs::write(path, format!("PID,LKP\n{pid},{lkp}\n")).unwrap();
+        assert!(validate_file(path, &curves, false).is_ok());
+        fs::write(path, format!("pid,serial\n{pid},{lkp}\n")).unwrap();
+        assert!(check_validate_file(path, &curves).is_err());
+
+        // Without a header the first row is a key, and a quoted field spanning lines shifts the line numbers
+        fs::write(path, format!("{pid},{spk},spk\n\"{pid}\",\"{lkp}\n\",lkp\n{pid},{lkp},lkp\n")).unwrap();
+        let results = check_validate_file(path, &curves).unwrap();
+        let lines: Vec<(u64, RowStatus)> = results.iter().map(|result| (result.line, result.status)).collect();
+        assert_eq!(lines, [(1, RowStatus::Valid), (2, RowStatus::Valid), (4, RowStatus::Valid)]);
+
+        fs::remove_file(path).unwrap();
+    }
 }
@@ -1,44 +1,66 @@
 //! Elliptic curve point operations
 
 use num_bigint::BigUint;
-use num_traits::Zero;
+use num_traits::{One, Zero};
 
 use super::mod_inverse;
 
-/// Elliptic curve point
+/// Elliptic curve point on `y^2 = x^3 + ax + b (mod p)`
 #[derive(Clone, Debug)]
 pub struct EllipticCurvePoint {
     pub x: BigUint,
     pub y: BigUint,
     pub a: BigUint,
+    pub b: BigUint,
     pub p: BigUint,
     pub infinity: bool,
 }
 
 impl EllipticCurvePoint {
     /// Create a new point on the curve
-    pub fn new(x: BigUint, y: BigUint, a: BigUint, p: BigUint) -> Self {
+    pub fn new(x: BigUint, y: BigUint, a: BigUint, b: BigUint, p: BigUint) -> Self {
         Self {
             x,
             y,
             a,
+            b,
             p,
             infinity: false,
         }
     }
     
     /// Create point at infinity
-    pub fn infinity(a: BigUint, p: BigUint) -> Self {
+    pub fn infinity(a: BigUint, b: BigUint, p: BigUint) -> Self {
         Self {
             x: BigUint::zero(),
             y: BigUint::zero(),
             a,
+            b,
             p,
             infinity: true,
         }
     }
     
+    /// Point at infinity on the same curve as `self`
+    fn identity(&self) -> Self {
+        Self::infinity(self.a.clone(), self.b.clone(), self.p.clone())
+    }
+    
+    /// Check whether the point satisfies the curve equation (infinity always does)
+    pub fn is_on_curve(&self) -> bool {
+        if self.infinity {
+            return true;
+        }
+        let p = &self.p;
+        let lhs = (&self.y * &self.y) % p;
+        let rhs = (&self.x * &self.x * &self.x + &self.a * &self.x + &self.b) % p;
+        lhs == rhs
+    }
+    
     /// Point addition on elliptic curve
+    ///
+    /// A slope denominator with no inverse mod `p` (a vertical tangent, or any
+    /// shared factor when malformed parameters make `p` composite) gives infinity.
     pub fn add(&self, other: &EllipticCurvePoint) -> EllipticCurvePoint {
         if self.infinity {
             return other.clone();
@@ -54,25 +76,32 @@ impl EllipticCurvePoint {
                 // Point doubling: s = (3*x^2 + a) / (2*y) mod p
                 let numerator = (BigUint::from(3u32) * &self.x * &self.x + &self.a) % p;
                 let denominator = (BigUint::from(2u32) * &self.y) % p;
-                let inv = mod_inverse(&denominator, p).expect("Failed to compute modular inverse");
+                if denominator.is_zero() {
+                    return self.identity();
+                }
+                let Some(inv) = mod_inverse(&denominator, p) else {
+                    return self.identity();
+                };
                 (numerator * inv) % p
             } else {
                 // Points are inverse of each other
-                return EllipticCurvePoint::infinity(self.a.clone(), self.p.clone());
+                return self.identity();
             }
         } else {
             // Point addition: s = (y2 - y1) / (x2 - x1) mod p
-            let numerator = if &other.y >= &self.y {
+            let numerator = if other.y >= self.y {
                 (&other.y - &self.y) % p
             } else {
                 (p + &other.y - &self.y) % p
             };
-            let denominator = if &other.x >= &self.x {
+            let denominator = if other.x >= self.x {
                 (&other.x - &self.x) % p
             } else {
                 (p + &other.x - &self.x) % p
             };
-            let inv = mod_inverse(&denominator, p).expect("Failed to compute modular inverse");
+            let Some(inv) = mod_inverse(&denominator, p) else {
+                return self.identity();
+            };
             (numerator * inv) % p
         };
         
@@ -86,7 +115,7 @@ impl EllipticCurvePoint {
         };
         
         // y3 = s * (x1 - x3) - y1 mod p
-        let x_diff = if &self.x >= &x3 {
+        let x_diff = if self.x >= x3 {
             (&self.x - &x3) % p
         } else {
             (p + &self.x - &x3) % p
@@ -98,27 +127,268 @@ impl EllipticCurvePoint {
             (p + s_times_diff - &self.y) % p
         };
         
-        EllipticCurvePoint::new(x3, y3, self.a.clone(), self.p.clone())
+        EllipticCurvePoint::new(x3, y3, self.a.clone(), self.b.clone(), self.p.clone())
+    }
+    
+    /// Additive inverse: the reflection `(x, p - y)`, with infinity its own inverse
+    pub fn negate(&self) -> EllipticCurvePoint {
+        if self.infinity {
+            return self.clone();
+        }
+        let y = (&self.p - &self.y % &self.p) % &self.p;
+        EllipticCurvePoint::new(self.x.clone(), y, self.a.clone(), self.b.clone(), self.p.clone())
+    }
+    
+    /// Point subtraction: `self + (-other)`
+    pub fn sub(&self, other: &EllipticCurvePoint) -> EllipticCurvePoint {
+        self.add(&other.negate())
     }
     
     /// Scalar multiplication using double-and-add algorithm
+    ///
+    /// Runs in Jacobian coordinates so only one modular inversion is needed,
+    /// when converting the result back to affine.
     pub fn mul(&self, scalar: &BigUint) -> EllipticCurvePoint {
-        if scalar.is_zero() {
-            return EllipticCurvePoint::infinity(self.a.clone(), self.p.clone());
+        if scalar.is_zero() || self.infinity {
+            return self.identity();
         }
         
-        let mut result = EllipticCurvePoint::infinity(self.a.clone(), self.p.clone());
-        let mut addend = self.clone();
-        let mut k = scalar.clone();
+        let mut result = JacobianPoint::infinity();
+        let mut addend = JacobianPoint::from_affine(self);
+        
+        for i in 0..scalar.bits() {
+            if scalar.bit(i) {
+                result = result.add(&addend, &self.a, &self.p);
+            }
+            addend = addend.double(&self.a, &self.p);
+        }
+        
+        result.to_affine(self)
+    }
+}
+
+/// Point in Jacobian coordinates: `(X, Y, Z)` represents `(X/Z^2, Y/Z^3)`, `Z = 0` is infinity
+#[derive(Clone)]
+struct JacobianPoint {
+    x: BigUint,
+    y: BigUint,
+    z: BigUint,
+}
+
+/// `(a - b) mod p` for `a, b < p`
+fn sub_mod(a: &BigUint, b: &BigUint, p: &BigUint) -> BigUint {
+    if a >= b {
+        a - b
+    } else {
+        p + a - b
+    }
+}
+
+impl JacobianPoint {
+    fn infinity() -> Self {
+        Self {
+            x: BigUint::one(),
+            y: BigUint::one(),
+            z: BigUint::zero(),
+        }
+    }
+    
+    fn from_affine(point: &EllipticCurvePoint) -> Self {
+        if point.infinity {
+            return Self::infinity();
+        }
+        Self {
+            x: &point.x % &point.p,
+            y: &point.y % &point.p,
+            z: BigUint::one(),
+        }
+    }
+    
+    fn is_infinity(&self) -> bool {
+        self.z.is_zero()
+    }
+    
+    /// Convert back to affine coordinates on the curve of `curve`, or infinity if `Z`
+    /// has no inverse mod `p`
+    fn to_affine(&self, curve: &EllipticCurvePoint) -> EllipticCurvePoint {
+        if self.is_infinity() {
+            return curve.identity();
+        }
+        let p = &curve.p;
+        let Some(z_inv) = mod_inverse(&self.z, p) else {
+            return curve.identity();
+        };
+        let z_inv2 = (&z_inv * &z_inv) % p;
+        let x = (&self.x * &z_inv2) % p;
+        let y = (&self.y * z_inv2 * z_inv) % p;
+        EllipticCurvePoint::new(x, y, curve.a.clone(), curve.b.clone(), p.clone())
+    }
+    
+    /// Point doubling for `y^2 = x^3 + ax + b`
+    fn double(&self, a: &BigUint, p: &BigUint) -> Self {
+        if self.is_infinity() || self.y.is_zero() {
+            return Self::infinity();
+        }
         
-        while !k.is_zero() {
-            if (&k & BigUint::from(1u32)) == BigUint::from(1u32) {
+        let y2 = (&self.y * &self.y) % p;
+        // S = 4 * X * Y^2
+        let s = (BigUint::from(4u32) * &self.x * &y2) % p;
+        // M = 3 * X^2 + a * Z^4
+        let z2 = (&self.z * &self.z) % p;
+        let m = (BigUint::from(3u32) * &self.x * &self.x + a * &z2 * &z2) % p;
+        // X' = M^2 - 2S
+        let x3 = sub_mod(&((&m * &m) % p), &((BigUint::from(2u32) * &s) % p), p);
+        // Y' = M * (S - X') - 8 * Y^4
+        let y4_8 = (BigUint::from(8u32) * &y2 * &y2) % p;
+        let y3 = sub_mod(&((m * sub_mod(&s, &x3, p)) % p), &y4_8, p);
+        // Z' = 2 * Y * Z
+        let z3 = (BigUint::from(2u32) * &self.y * &self.z) % p;
+        
+        Self { x: x3, y: y3, z: z3 }
+    }
+    
+    /// Point addition, falling back to doubling when both points are equal
+    fn add(&self, other: &Self, a: &BigUint, p: &BigUint) -> Self {
+        if self.is_infinity() {
+            return other.clone();
+        }
+        if other.is_infinity() {
+            return self.clone();
+        }
+        
+        let z1_2 = (&self.z * &self.z) % p;
+        let z2_2 = (&other.z * &other.z) % p;
+        let u1 = (&self.x * &z2_2) % p;
+        let u2 = (&other.x * &z1_2) % p;
+        let s1 = (&self.y * &z2_2 * &other.z) % p;
+        let s2 = (&other.y * &z1_2 * &self.z) % p;
+        
+        if u1 == u2 {
+            if s1 == s2 {
+                return self.double(a, p);
+            }
+            return Self::infinity();
+        }
+        
+        let h = sub_mod(&u2, &u1, p);
+        let r = sub_mod(&s2, &s1, p);
+        let h2 = (&h * &h) % p;
+        let h3 = (&h2 * &h) % p;
+        let u1h2 = (&u1 * &h2) % p;
+        
+        // X3 = R^2 - H^3 - 2 * U1 * H^2
+        let x3 = sub_mod(
+            &sub_mod(&((&r * &r) % p), &h3, p),
+            &((BigUint::from(2u32) * &u1h2) % p),
+            p,
+        );
+        // Y3 = R * (U1 * H^2 - X3) - S1 * H^3
+        let y3 = sub_mod(&((r * sub_mod(&u1h2, &x3, p)) % p), &((s1 * h3) % p), p);
+        // Z3 = H * Z1 * Z2
+        let z3 = (h * &self.z * &other.z) % p;
+        
+        Self { x: x3, y: y3, z: z3 }
+    }
+}
+
+// These check against the built-in curves, which live in `types`
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::keygen::random_nonce;
+    use crate::types::{LKPCurve, SPKCurve};
+    
+    fn same(a: &EllipticCurvePoint, b: &EllipticCurvePoint) -> bool {
+        a.infinity == b.infinity && (a.infinity || (a.x == b.x && a.y == b.y))
+    }
+    
+    /// Reference double-and-add using only affine `add`
+    fn mul_affine(point: &EllipticCurvePoint, scalar: &BigUint) -> EllipticCurvePoint {
+        let mut result = point.identity();
+        let mut addend = point.clone();
+        for i in 0..scalar.bits() {
+            if scalar.bit(i) {
                 result = result.add(&addend);
             }
             addend = addend.add(&addend);
-            k >>= 1;
         }
-        
         result
     }
+    
+    /// Compare `mul` against `mul_affine` for `count` scalars drawn uniformly from
+    /// `[1, n)`, plus the group order boundary
+    fn check_random_scalars(count: usize) {
+        let mut rng = rand::thread_rng();
+        
+        for curve in [SPKCurve::params(), LKPCurve::params()] {
+            let g = curve.generator();
+            let mut scalars: Vec<BigUint> = (0..count).map(|_| random_nonce(&mut rng, &curve.n)).collect();
+            scalars.extend([&curve.n - 1u32, curve.n.clone(), &curve.n + 1u32]);
+            for k in &scalars {
+                let product = g.mul(k);
+                assert!(product.is_on_curve());
+                assert!(same(&product, &mul_affine(&g, k)), "k = {}", k);
+            }
+        }
+    }
+    
+    #[test]
+    fn test_jacobian_mul_matches_affine() {
+        for curve in [SPKCurve::params(), LKPCurve::params()] {
+            let g = curve.generator();
+            
+            // Small multiples against a running affine sum
+            let mut expected = g.identity();
+            for k in 0u32..1000 {
+                assert!(same(&g.mul(&BigUint::from(k)), &expected), "k = {}", k);
+                expected = expected.add(&g);
+            }
+        }
+        
+        check_random_scalars(50);
+    }
+    
+    #[test]
+    #[ignore = "slow; run with --release -- --ignored"]
+    fn test_jacobian_mul_matches_affine_many() {
+        check_random_scalars(5000);
+    }
+    
+    #[test]
+    fn test_negate_and_sub() {
+        for curve in [SPKCurve::params(), LKPCurve::params()] {
+            let g = curve.generator();
+            let p = g.mul(&BigUint::from(12345u32));
+            
+            let neg = p.negate();
+            assert!(neg.is_on_curve());
+            assert!(p.add(&neg).infinity);
+            assert!(neg.add(&p).infinity);
+            assert!(p.sub(&p).infinity);
+            assert!(same(&neg.negate(), &p));
+            assert!(g.identity().negate().infinity);
+            
+            // (n - 1) * G is -G
+            assert!(same(&g.mul(&(&curve.n - 1u32)), &g.negate()));
+            assert!(same(&p.add(&g).sub(&g), &p));
+            assert!(same(&g.identity().sub(&p), &neg));
+        }
+    }
+    
+    #[test]
+    fn test_non_invertible_denominator_gives_infinity() {
+        for curve in [SPKCurve::params(), LKPCurve::params()] {
+            let g = curve.generator();
+            assert!(g.mul(&curve.n).infinity);
+            assert!(mul_affine(&g, &curve.n).infinity);
+        }
+        
+        // p = 15 is composite: 4 - 1 = 3 and 2 * 3 = 6 share a factor with it
+        let point = |x: u32, y: u32| {
+            EllipticCurvePoint::new(x.into(), y.into(), BigUint::one(), BigUint::one(), BigUint::from(15u32))
+        };
+        assert!(point(1, 1).add(&point(4, 2)).infinity);
+        assert!(point(2, 3).add(&point(2, 3)).infinity);
+        assert!(point(2, 0).add(&point(2, 0)).infinity);
+    }
 }
@@ -1,11 +1,58 @@
 //! Key encoding and decoding functions
 
-use crate::types::KCHARS;
+use alloc::string::{String, ToString};
 use num_bigint::BigUint;
 use num_traits::Zero;
 
+/// Character set for key encoding (base-24)
+pub const KCHARS: &str = "BCDFGHJKMPQRTVWXY2346789";
+
+/// Number of base-24 characters in a generated key, not counting dashes
+pub const KEY_LEN: usize = 35;
+
+/// Layout of an encoded product key
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodeOptions {
+    /// Characters per group; `0` disables grouping
+    pub group_size: usize,
+    /// Inserted between groups
+    pub separator: String,
+    /// Number of base-24 characters, left-padded
+    pub total_len: usize,
+}
+
+impl Default for EncodeOptions {
+    /// 35 characters in groups of 5 separated by dashes
+    fn default() -> Self {
+        Self {
+            group_size: 5,
+            separator: "-".to_string(),
+            total_len: KEY_LEN,
+        }
+    }
+}
+
+impl EncodeOptions {
+    /// The default 35 characters without any separators
+    pub fn ungrouped() -> Self {
+        Self {
+            group_size: 0,
+            separator: String::new(),
+            ..Self::default()
+        }
+    }
+}
+
 /// Encode integer to product key format (base-24 with dashes)
 pub fn encode_pkey(n: &BigUint) -> String {
+    encode_pkey_with(n, &EncodeOptions::default())
+}
+
+/// Encode integer to product key format with a custom grouping
+///
+/// [`decode_pkey`] reads the result back only if the separator is a dash or
+/// whitespace, since those are the only characters it strips.
+pub fn encode_pkey_with(n: &BigUint, opts: &EncodeOptions) -> String {
     if n.is_zero() {
         return String::new();
     }
@@ -22,16 +69,16 @@ pub fn encode_pkey(n: &BigUint) -> String {
         num /= &base;
     }
     
-    // Pad to 35 characters
-    while out.len() < 35 {
+    // Pad to the full key length
+    while out.len() < opts.total_len {
         out.insert(0, KCHARS.chars().next().unwrap());
     }
     
-    // Split into groups of 5 with dashes
+    // Split into groups
     let mut result = String::new();
     for (i, ch) in out.chars().enumerate() {
-        if i > 0 && i % 5 == 0 {
-            result.push('-');
+        if opts.group_size > 0 && i > 0 && i % opts.group_size == 0 {
+            result.push_str(&opts.separator);
         }
         result.push(ch);
     }
@@ -39,11 +86,55 @@ pub fn encode_pkey(n: &BigUint) -> String {
     result
 }
 
-/// Decode product key format to integer
+/// Normalize a pasted key: drop all whitespace and uppercase letters
+pub fn normalize_key(key: &str) -> String {
+    key.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Check that `key` is a full-length product key, ignoring dashes
+///
+/// Reports the first character outside `KCHARS` with its 1-based position in the
+/// normalized key, so typos are caught before decoding turns them into a
+/// meaningless number.
+pub fn verify_pkey_format(key: &str) -> anyhow::Result<()> {
+    let len = pkey_len(key)?;
+    if len != KEY_LEN {
+        anyhow::bail!("key has {} characters, expected {}", len, KEY_LEN);
+    }
+    
+    Ok(())
+}
+
+/// Number of characters in `key`, not counting dashes
+///
+/// Fails like `verify_pkey_format` on a character outside `KCHARS`, but accepts any length.
+pub fn pkey_len(key: &str) -> anyhow::Result<usize> {
+    let key = normalize_key(key);
+    for (i, ch) in key.chars().enumerate() {
+        if ch != '-' && !KCHARS.contains(ch) {
+            // Look-alikes are left out of the alphabet, so they are never a valid guess
+            let note = if matches!(ch, 'O' | '0' | 'I' | '1') {
+                " (O, 0, I and 1 never appear in keys)"
+            } else {
+                ""
+            };
+            anyhow::bail!("invalid character '{}' at position {}{}", ch, i + 1, note);
+        }
+    }
+    
+    Ok(key.chars().filter(|&ch| ch != '-').count())
+}
+
+/// Decode product key format to integer, with or without dashes
+///
+/// The key is normalized first, so lowercase letters and stray whitespace are accepted.
 pub fn decode_pkey(key: &str) -> anyhow::Result<BigUint> {
-    let key_string = key.replace('-', "");
+    let key_string = normalize_key(key).replace('-', "");
     
-    if key_string.len() % 5 != 0 {
+    if !key_string.len().is_multiple_of(5) {
         anyhow::bail!("Bad key length");
     }
     
@@ -62,6 +153,7 @@ pub fn decode_pkey(key: &str) -> anyhow::Result<BigUint> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::format;
     
     #[test]
     fn test_encode_decode() {
@@ -70,4 +162,55 @@ mod tests {
         let decoded = decode_pkey(&encoded).unwrap();
         assert_eq!(num, decoded);
     }
+    
+    #[test]
+    fn test_encode_options() {
+        let num = BigUint::from(12345678901234567890u64);
+        let dashed = encode_pkey(&num);
+        let plain = encode_pkey_with(&num, &EncodeOptions::ungrouped());
+        assert_eq!(dashed.len(), 41);
+        assert_eq!(plain.len(), 35);
+        assert_eq!(plain, dashed.replace('-', ""));
+        assert_eq!(decode_pkey(&plain).unwrap(), num);
+        
+        let opts = EncodeOptions { group_size: 7, separator: " ".to_string(), total_len: 35 };
+        assert_eq!(encode_pkey_with(&num, &opts).split(' ').count(), 5);
+        assert_eq!(decode_pkey(&encode_pkey_with(&num, &opts)).unwrap(), num);
+        
+        let opts = EncodeOptions { separator: ".".to_string(), ..EncodeOptions::default() };
+        assert!(decode_pkey(&encode_pkey_with(&num, &opts)).is_err());
+    }
+    
+    #[test]
+    fn test_verify_pkey_format() {
+        let key = encode_pkey(&BigUint::from(12345678901234567890u64));
+        assert!(verify_pkey_format(&key).is_ok());
+        assert!(verify_pkey_format(&key.replace('-', "")).is_ok());
+        
+        let mut typo = key.clone();
+        typo.replace_range(11..12, "Z");
+        assert_eq!(
+            verify_pkey_format(&typo).unwrap_err().to_string(),
+            "invalid character 'Z' at position 12"
+        );
+        assert_eq!(
+            verify_pkey_format(&key[..35]).unwrap_err().to_string(),
+            "key has 30 characters, expected 35"
+        );
+    }
+    
+    #[test]
+    fn test_decode_normalizes_input() {
+        let num = BigUint::from(12345678901234567890u64);
+        let key = encode_pkey(&num);
+        let pasted = format!("  {} \n", key.to_lowercase().replace('-', " - "));
+        assert_eq!(decode_pkey(&pasted).unwrap(), num);
+        assert!(verify_pkey_format(&pasted).is_ok());
+        assert_eq!(decode_pkey(&key.replace('-', "\t")).unwrap(), num);
+        
+        let mut typo = key.clone();
+        typo.replace_range(0..1, "o");
+        assert!(decode_pkey(&typo).is_err());
+        assert!(verify_pkey_format(&typo).unwrap_err().to_string().contains("never appear"));
+    }
 }
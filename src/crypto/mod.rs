@@ -1,21 +1,29 @@
 //! Cryptographic operations module
+//!
+//! Everything here is `no_std` + `alloc` compatible and available without the `std` feature.
 
 pub mod curve;
 pub mod encoding;
 pub mod rc4;
 
 pub use curve::EllipticCurvePoint;
-pub use encoding::{decode_pkey, encode_pkey};
+pub use encoding::{decode_pkey, encode_pkey, encode_pkey_with, normalize_key, pkey_len, verify_pkey_format, EncodeOptions, KEY_LEN};
 pub use rc4::rc4_crypt;
 
+use alloc::vec::Vec;
 use num_bigint::BigUint;
-use num_traits::{One, Zero};
+use num_traits::Zero;
 
 /// Convert BigUint to little-endian bytes with specified length
-pub fn bigint_to_bytes_le(n: &BigUint, length: usize) -> Vec<u8> {
+///
+/// Fails rather than truncating if `n` needs more than `length` bytes.
+pub fn bigint_to_bytes_le(n: &BigUint, length: usize) -> anyhow::Result<Vec<u8>> {
     let mut bytes = n.to_bytes_le();
+    if bytes.len() > length {
+        anyhow::bail!("Value needs {} bytes but only {} are available", bytes.len(), length);
+    }
     bytes.resize(length, 0);
-    bytes
+    Ok(bytes)
 }
 
 /// Convert little-endian bytes to BigUint
@@ -23,30 +31,125 @@ pub fn bytes_to_bigint_le(data: &[u8]) -> BigUint {
     BigUint::from_bytes_le(data)
 }
 
+/// Encode a string as UTF-16LE bytes
+pub fn encode_utf16_le(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+/// RC4 key that encrypts the keys issued for `pid`
+///
+/// The first 5 bytes of the MD5 digest of the UTF-16LE PID, zero-padded to 16 bytes.
+pub fn derive_rc4_key(pid: &str) -> [u8; 16] {
+    let digest = md5::compute(encode_utf16_le(pid));
+    let mut key = [0u8; 16];
+    key[..5].copy_from_slice(&digest[..5]);
+    key
+}
+
+/// Compare two byte strings in time that depends only on their lengths
+///
+/// Used when checking attacker-supplied keys (e.g. through the HTTP API) so that
+/// response timing does not reveal how many leading bytes of a signature or SPKID
+/// matched. Lengths are not secret: callers compare fixed-width encodings.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    core::hint::black_box(diff) == 0
+}
+
 /// Calculate modular multiplicative inverse using Extended Euclidean Algorithm
+///
+/// Returns `None` when `a` and `m` are not coprime.
 pub fn mod_inverse(a: &BigUint, m: &BigUint) -> Option<BigUint> {
     use num_bigint::BigInt;
-    use num_traits::ToPrimitive;
-    
-    fn extended_gcd(a: BigInt, b: BigInt) -> (BigInt, BigInt, BigInt) {
-        if a.is_zero() {
-            return (b, BigInt::from(0), BigInt::from(1));
-        }
-        let (gcd, x1, y1) = extended_gcd(&b % &a, a.clone());
-        let x = y1 - (&b / &a) * &x1;
-        let y = x1;
-        (gcd, x, y)
-    }
+    use num_integer::Integer;
+    use num_traits::One;
     
-    let a_int = BigInt::from(a.clone());
     let m_int = BigInt::from(m.clone());
     
-    let (gcd, x, _) = extended_gcd(&a_int % &m_int, m_int.clone());
-    if gcd != BigInt::from(1) {
+    // Iterative extended GCD, tracking only the Bezout coefficient of `a`
+    let (mut old_r, mut r) = (BigInt::from(a % m), m_int.clone());
+    let (mut old_s, mut s) = (BigInt::one(), BigInt::zero());
+    while !r.is_zero() {
+        let (q, rem) = old_r.div_rem(&r);
+        old_r = core::mem::replace(&mut r, rem);
+        let next_s = &old_s - &q * &s;
+        old_s = core::mem::replace(&mut s, next_s);
+    }
+    
+    if !old_r.is_one() {
         return None;
     }
     
     // Convert result to positive value in range [0, m)
-    let result = ((x % &m_int) + &m_int) % &m_int;
+    let result = old_s.mod_floor(&m_int);
     Some(result.to_biguint().unwrap())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    
+    #[test]
+    fn test_bigint_to_bytes_le_bounds() {
+        let fits = (BigUint::from(1u32) << 56u32) - 1u32;
+        assert_eq!(bigint_to_bytes_le(&fits, 7).unwrap(), vec![0xFF; 7]);
+        assert_eq!(bigint_to_bytes_le(&BigUint::from(1u32), 3).unwrap(), vec![1, 0, 0]);
+        
+        let overflows = BigUint::from(1u32) << 56u32;
+        assert!(bigint_to_bytes_le(&overflows, 7).is_err());
+    }
+    
+    #[test]
+    fn test_derive_rc4_key() {
+        assert_eq!(encode_utf16_le("A\u{e9}"), [0x41, 0x00, 0xE9, 0x00]);
+        
+        let key = derive_rc4_key("00490-92005-99454-AT527");
+        assert_eq!(key[..5], [0xE4, 0x42, 0x9D, 0x46, 0x2A]);
+        assert_eq!(key[5..], [0u8; 11]);
+    }
+    
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_ct_eq_matches_eq() {
+        use rand::Rng;
+        
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let a: [u8; 8] = rng.gen();
+            // Mostly-equal inputs exercise every differing position
+            let mut b = a;
+            if rng.gen_bool(0.5) {
+                b[rng.gen_range(0..8)] ^= 1 << rng.gen_range(0..8);
+            }
+            assert_eq!(ct_eq(&a, &b), a == b);
+        }
+        assert!(!ct_eq(&[1, 2], &[1, 2, 3]));
+    }
+    
+    #[test]
+    fn test_mod_inverse() {
+        assert_eq!(mod_inverse(&BigUint::from(3u32), &BigUint::from(11u32)), Some(BigUint::from(4u32)));
+        // Not coprime
+        assert_eq!(mod_inverse(&BigUint::from(6u32), &BigUint::from(9u32)), None);
+        // `a` larger than `m`
+        assert_eq!(mod_inverse(&BigUint::from(14u32), &BigUint::from(11u32)), Some(BigUint::from(4u32)));
+    }
+    
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_mod_inverse_curve_primes() {
+        use crate::types::{LKPCurve, SPKCurve};
+        
+        for p in [SPKCurve::p(), LKPCurve::p()] {
+            for a in [BigUint::from(2u32), BigUint::from(123456789u64), &p - 1u32, &p + 5u32] {
+                let inv = mod_inverse(&a, &p).unwrap();
+                assert!(inv < p);
+                assert_eq!((&a * &inv) % &p, BigUint::from(1u32));
+            }
+        }
+    }
+}
@@ -1,14 +1,32 @@
 //! RC4 encryption/decryption
 
+use alloc::vec::Vec;
+
 /// RC4 encryption/decryption (symmetric)
-pub fn rc4_crypt(key: &[u8], data: &[u8]) -> Vec<u8> {
-    let mut s: Vec<u8> = (0..=255).collect();
-    let mut j: usize = 0;
+///
+/// This only exists because the Microsoft key format obfuscates key data with RC4
+/// under a PID-derived key; it provides no secrecy and must not be used to protect
+/// anything.
+///
+/// Every state access at a secret index (`j` and the output index) touches the whole
+/// table, so the memory access pattern only depends on the lengths of `key` and `data`.
+///
+/// Fails if `key` is empty, since the key schedule cycles through it.
+pub fn rc4_crypt(key: &[u8], data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if key.is_empty() {
+        anyhow::bail!("RC4 key must not be empty");
+    }
     
-    // Key scheduling algorithm (KSA)
+    let mut s = [0u8; 256];
+    for (i, x) in s.iter_mut().enumerate() {
+        *x = i as u8;
+    }
+    let mut j: u8 = 0;
+    
+    // Key scheduling algorithm (KSA); `i` is public, `j` is not
     for i in 0..256 {
-        j = (j + s[i] as usize + key[i % key.len()] as usize) % 256;
-        s.swap(i, j);
+        j = j.wrapping_add(s[i]).wrapping_add(key[i % key.len()]);
+        swap_ct(&mut s, i, j);
     }
     
     // Pseudo-random generation algorithm (PRGA)
@@ -18,13 +36,36 @@ pub fn rc4_crypt(key: &[u8], data: &[u8]) -> Vec<u8> {
     
     for &byte in data {
         i = (i + 1) % 256;
-        j = (j + s[i] as usize) % 256;
-        s.swap(i, j);
-        let k = s[(s[i] as usize + s[j] as usize) % 256];
+        j = j.wrapping_add(s[i]);
+        swap_ct(&mut s, i, j);
+        let k = load_ct(&s, s[i].wrapping_add(load_ct(&s, j)));
         result.push(byte ^ k);
     }
     
-    result
+    Ok(result)
+}
+
+/// `0xFF` if `a == b`, else `0`, without branching
+fn eq_mask(a: u8, b: u8) -> u8 {
+    let d = (a ^ b) as u32;
+    (d.wrapping_sub(1) >> 8) as u8
+}
+
+/// `s[idx]`, reading every entry
+fn load_ct(s: &[u8; 256], idx: u8) -> u8 {
+    s.iter()
+        .enumerate()
+        .fold(0, |acc, (k, &x)| acc | (x & eq_mask(k as u8, idx)))
+}
+
+/// Swap `s[i]` and `s[j]`, writing every entry for the secret `j`
+fn swap_ct(s: &mut [u8; 256], i: usize, j: u8) {
+    let si = s[i];
+    s[i] = load_ct(s, j);
+    for (k, x) in s.iter_mut().enumerate() {
+        let mask = eq_mask(k as u8, j);
+        *x = (*x & !mask) | (si & mask);
+    }
 }
 
 #[cfg(test)]
@@ -36,9 +77,30 @@ mod tests {
         let key = b"test_key";
         let plaintext = b"Hello, World!";
         
-        let encrypted = rc4_crypt(key, plaintext);
-        let decrypted = rc4_crypt(key, &encrypted);
+        let encrypted = rc4_crypt(key, plaintext).unwrap();
+        let decrypted = rc4_crypt(key, &encrypted).unwrap();
         
         assert_eq!(plaintext, &decrypted[..]);
     }
+    
+    #[test]
+    fn test_rc4_known_answers() {
+        // Test vectors from the original RC4 posting
+        assert_eq!(rc4_crypt(b"Key", b"Plaintext").unwrap(), [0xBB, 0xF3, 0x16, 0xE8, 0xD9, 0x40, 0xAF, 0x0A, 0xD3]);
+        assert_eq!(rc4_crypt(b"Wiki", b"pedia").unwrap(), [0x10, 0x21, 0xBF, 0x04, 0x20]);
+        assert_eq!(
+            rc4_crypt(b"Secret", b"Attack at dawn").unwrap(),
+            [0x45, 0xA0, 0x1F, 0x64, 0x5F, 0xC3, 0x5B, 0x38, 0x35, 0x52, 0x54, 0x4B, 0x9B, 0xF5]
+        );
+    }
+    
+    #[test]
+    fn test_rc4_empty_key() {
+        assert!(rc4_crypt(&[], b"data").is_err());
+    }
+    
+    #[test]
+    fn test_rc4_empty_data() {
+        assert!(rc4_crypt(b"test_key", &[]).unwrap().is_empty());
+    }
 }
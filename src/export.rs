@@ -0,0 +1,166 @@
+//! Offline license package (`.bin`) export and import
+//!
+//! A package bundles a generated SPK, the optional LKP and their metadata into
+//! a small binary file, written by `--export` and read back by `--import` and
+//! `decode-lkp --package`. All integers are little-endian; strings are UTF-8
+//! prefixed with their byte length as a `u16`.
+//!
+//! | Offset | Size | Field                                               |
+//! |--------|------|-----------------------------------------------------|
+//! | 0      | 4    | Magic `LRDS`                                        |
+//! | 4      | 1    | Format version (`1`)                                |
+//! | 5      | 8    | Creation time, Unix seconds (`u64`)                 |
+//! | 13     | 4    | License count (`u32`, `0` when there is no LKP)     |
+//! | 17     | var  | PID (string)                                        |
+//! |        | var  | SPK (string)                                        |
+//! |        | var  | License code, e.g. `029_10_2` (string, empty if none) |
+//! |        | var  | LKP (string, empty if none)                         |
+//!
+//! Readers must reject unknown versions and trailing bytes.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// File signature at the start of every package
+pub const MAGIC: &[u8; 4] = b"LRDS";
+/// Current package format version
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Keys and metadata for one PID, as stored in a package file
+#[derive(Debug, Clone, PartialEq)]
+pub struct LicensePackage {
+    pub pid: String,
+    pub spk: String,
+    pub lkp: Option<PackagedLkp>,
+    /// Creation time in Unix seconds
+    pub timestamp: u64,
+}
+
+/// LKP part of a [`LicensePackage`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackagedLkp {
+    pub license_code: String,
+    pub count: u32,
+    pub lkp: String,
+}
+
+impl LicensePackage {
+    /// Package stamped with the current time
+    pub fn new(pid: &str, spk: &str, lkp: Option<PackagedLkp>) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self {
+            pid: pid.to_string(),
+            spk: spk.to_string(),
+            lkp,
+            timestamp,
+        }
+    }
+
+    /// Serialize to the binary layout described in the module docs
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let (license_code, count, lkp) = match &self.lkp {
+            Some(lkp) => (lkp.license_code.as_str(), lkp.count, lkp.lkp.as_str()),
+            None => ("", 0, ""),
+        };
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&self.timestamp.to_le_bytes());
+        bytes.extend_from_slice(&count.to_le_bytes());
+        for field in [self.pid.as_str(), self.spk.as_str(), license_code, lkp] {
+            let len = u16::try_from(field.len())
+                .map_err(|_| anyhow::anyhow!("Package field is too long ({} bytes)", field.len()))?;
+            bytes.extend_from_slice(&len.to_le_bytes());
+            bytes.extend_from_slice(field.as_bytes());
+        }
+        Ok(bytes)
+    }
+
+    /// Parse a package produced by [`LicensePackage::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = Reader { bytes };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            anyhow::bail!("Not a license package (bad magic)");
+        }
+        let version = reader.take(1)?[0];
+        if version != FORMAT_VERSION {
+            anyhow::bail!("Unsupported license package version {}", version);
+        }
+        let timestamp = u64::from_le_bytes(reader.take(8)?.try_into()?);
+        let count = u32::from_le_bytes(reader.take(4)?.try_into()?);
+        let pid = reader.string()?;
+        let spk = reader.string()?;
+        let license_code = reader.string()?;
+        let lkp = reader.string()?;
+
+        if !reader.bytes.is_empty() {
+            anyhow::bail!("Trailing bytes after license package");
+        }
+
+        let lkp = (!lkp.is_empty()).then_some(PackagedLkp {
+            license_code,
+            count,
+            lkp,
+        });
+        Ok(Self {
+            pid,
+            spk,
+            lkp,
+            timestamp,
+        })
+    }
+}
+
+/// Cursor over the remaining package bytes
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            anyhow::bail!("Truncated license package");
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn string(&mut self) -> anyhow::Result<String> {
+        let len = u16::from_le_bytes(self.take(2)?.try_into()?) as usize;
+        Ok(String::from_utf8(self.take(len)?.to_vec())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_round_trip() {
+        let lkp = PackagedLkp {
+            license_code: "029_10_2".to_string(),
+            count: 100,
+            lkp: "QD2VP-D79MF-XVFRH-HGQVT-PB79R-TDKJP-XHKXY".to_string(),
+        };
+        for lkp in [Some(lkp), None] {
+            let package = LicensePackage::new(
+                "00490-92005-99454-AT527",
+                "VXQF7-G69HX-YHCB2-BYD47-94G8Y-Y2CWH-WRTKH",
+                lkp,
+            );
+            let bytes = package.to_bytes().unwrap();
+            assert_eq!(LicensePackage::from_bytes(&bytes).unwrap(), package);
+
+            assert!(LicensePackage::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+            let mut trailing = bytes.clone();
+            trailing.push(0);
+            assert!(LicensePackage::from_bytes(&trailing).is_err());
+        }
+        assert!(LicensePackage::from_bytes(b"XXXX").is_err());
+    }
+}
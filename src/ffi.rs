@@ -0,0 +1,196 @@
+//! C ABI for embedding in non-Rust applications
+//!
+//! ABI contract:
+//! - All strings are null-terminated UTF-8. Input pointers must be non-null and valid for reads
+//!   up to their terminator.
+//! - Output buffers are written only on success and are always null-terminated. `out_len` is the
+//!   full buffer size in bytes including the terminator; generated keys need 42 bytes.
+//! - Functions never unwind across the boundary (a panic is caught and reported as
+//!   `LYSSA_ERR_FAILED`) and never write past `out_len`.
+//! - Return codes are the `LYSSA_*` constants below.
+//!
+//! The C header is generated with
+//! `cbindgen --config cbindgen.toml --output include/lyssa_rds_gen.h src/ffi.rs`; CI fails if the
+//! committed header is out of date.
+
+use crate::keygen;
+use crate::types::{CurveParams, KeyKind, LicenseInfo};
+use std::ffi::{c_char, c_int, CStr};
+
+/// Success (for validation: the key is valid)
+pub const LYSSA_OK: c_int = 0;
+/// Validation completed and the key does not match the PID
+pub const LYSSA_INVALID_KEY: c_int = 1;
+/// A required pointer argument was null
+pub const LYSSA_ERR_NULL_POINTER: c_int = -1;
+/// An input string was not valid UTF-8
+pub const LYSSA_ERR_INVALID_UTF8: c_int = -2;
+/// The output buffer is too small for the result and its terminator
+pub const LYSSA_ERR_BUFFER_TOO_SMALL: c_int = -3;
+/// Generation or validation failed (bad PID, unknown license type, exhausted attempts, internal panic, ...)
+pub const LYSSA_ERR_FAILED: c_int = -4;
+
+/// Borrow a C string argument as `&str`
+///
+/// # Safety
+/// `ptr` must be null or point to a null-terminated string.
+unsafe fn read_str<'a>(ptr: *const c_char) -> Result<&'a str, c_int> {
+    if ptr.is_null() {
+        return Err(LYSSA_ERR_NULL_POINTER);
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|_| LYSSA_ERR_INVALID_UTF8)
+}
+
+/// Copy `value` into `out` with a null terminator, refusing to overrun `out_len`
+///
+/// # Safety
+/// `out` must be null or valid for writes of `out_len` bytes.
+unsafe fn write_str(value: &str, out: *mut c_char, out_len: usize) -> c_int {
+    if out.is_null() {
+        return LYSSA_ERR_NULL_POINTER;
+    }
+    let bytes = value.as_bytes();
+    if bytes.len() + 1 > out_len {
+        return LYSSA_ERR_BUFFER_TOO_SMALL;
+    }
+    std::ptr::copy_nonoverlapping(bytes.as_ptr(), out as *mut u8, bytes.len());
+    *out.add(bytes.len()) = 0;
+    LYSSA_OK
+}
+
+/// Run an export body, reporting a panic as `LYSSA_ERR_FAILED` instead of unwinding into C
+fn guard(body: impl FnOnce() -> Result<c_int, c_int>) -> c_int {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(body))
+        .unwrap_or(Err(LYSSA_ERR_FAILED))
+        .unwrap_or_else(|code| code)
+}
+
+/// Generate an SPK (License Server ID) for `pid` into `out`
+///
+/// # Safety
+/// `pid` must be a valid C string and `out` valid for writes of `out_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn lyssa_generate_spk(
+    pid: *const c_char,
+    out: *mut c_char,
+    out_len: usize,
+) -> c_int {
+    guard(|| {
+        let pid = keygen::normalize_pid(read_str(pid)?);
+        let spk = keygen::generate_spk(&pid).map_err(|_| LYSSA_ERR_FAILED)?;
+        Ok(write_str(&spk, out, out_len))
+    })
+}
+
+/// Generate an LKP (License Key Pack) for `pid`, `count` and `license` (e.g. `029_10_2`) into `out`
+///
+/// # Safety
+/// `pid` and `license` must be valid C strings and `out` valid for writes of `out_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn lyssa_generate_lkp(
+    pid: *const c_char,
+    count: u32,
+    license: *const c_char,
+    out: *mut c_char,
+    out_len: usize,
+) -> c_int {
+    guard(|| {
+        let pid = keygen::normalize_pid(read_str(pid)?);
+        let license_info = LicenseInfo::parse(read_str(license)?).map_err(|_| LYSSA_ERR_FAILED)?;
+        let lkp = keygen::generate_lkp(
+            &pid,
+            count,
+            license_info.chid,
+            license_info.major_ver,
+            license_info.minor_ver,
+        )
+        .map_err(|_| LYSSA_ERR_FAILED)?;
+        Ok(write_str(&lkp, out, out_len))
+    })
+}
+
+/// Validate an SPK (`is_spk != 0`) or LKP (`is_spk == 0`) against `pid`
+///
+/// Returns `LYSSA_OK` when valid and `LYSSA_INVALID_KEY` when it does not match.
+///
+/// # Safety
+/// `pid` and `key` must be valid C strings.
+#[no_mangle]
+pub unsafe extern "C" fn lyssa_validate_tskey(
+    pid: *const c_char,
+    key: *const c_char,
+    is_spk: c_int,
+) -> c_int {
+    guard(|| {
+        let pid = keygen::normalize_pid(read_str(pid)?);
+        let key = read_str(key)?;
+        let kind = KeyKind::from_is_spk(is_spk != 0);
+        match keygen::validate_tskey(&pid, key, &CurveParams::for_kind(kind), kind) {
+            Ok(true) => Ok(LYSSA_OK),
+            Ok(false) => Ok(LYSSA_INVALID_KEY),
+            Err(_) => Err(LYSSA_ERR_FAILED),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    
+    const PID: &str = "00490-92005-99454-AT527";
+    
+    #[test]
+    fn test_ffi_round_trip() {
+        let pid = CString::new(PID).unwrap();
+        let license = CString::new("029_10_2").unwrap();
+        let mut spk = [0 as c_char; 64];
+        let mut lkp = [0 as c_char; 64];
+        
+        unsafe {
+            assert_eq!(lyssa_generate_spk(pid.as_ptr(), spk.as_mut_ptr(), spk.len()), LYSSA_OK);
+            assert_eq!(lyssa_validate_tskey(pid.as_ptr(), spk.as_ptr(), 1), LYSSA_OK);
+            
+            assert_eq!(
+                lyssa_generate_lkp(pid.as_ptr(), 10, license.as_ptr(), lkp.as_mut_ptr(), lkp.len()),
+                LYSSA_OK
+            );
+            assert_eq!(lyssa_validate_tskey(pid.as_ptr(), lkp.as_ptr(), 0), LYSSA_OK);
+            
+            // An LKP is not a valid SPK for the same PID
+            assert_eq!(lyssa_validate_tskey(pid.as_ptr(), lkp.as_ptr(), 1), LYSSA_INVALID_KEY);
+        }
+        
+        let spk = unsafe { CStr::from_ptr(spk.as_ptr()) }.to_str().unwrap();
+        assert_eq!(spk.len(), 41);
+    }
+    
+    #[test]
+    fn test_ffi_guards() {
+        let pid = CString::new(PID).unwrap();
+        let mut small = [0x7f as c_char; 41];
+        
+        unsafe {
+            assert_eq!(
+                lyssa_generate_spk(std::ptr::null(), small.as_mut_ptr(), small.len()),
+                LYSSA_ERR_NULL_POINTER
+            );
+            assert_eq!(
+                lyssa_generate_spk(pid.as_ptr(), small.as_mut_ptr(), small.len()),
+                LYSSA_ERR_BUFFER_TOO_SMALL
+            );
+        }
+        
+        // Nothing is written when the buffer is too small
+        assert!(small.iter().all(|&b| b == 0x7f));
+    }
+    
+    #[test]
+    fn test_ffi_guard_catches_panics() {
+        assert_eq!(guard(|| Ok(LYSSA_INVALID_KEY)), LYSSA_INVALID_KEY);
+        assert_eq!(guard(|| Err(LYSSA_ERR_INVALID_UTF8)), LYSSA_ERR_INVALID_UTF8);
+        assert_eq!(guard(|| panic!("boom")), LYSSA_ERR_FAILED);
+    }
+}
@@ -1,36 +1,81 @@
 //! Graphical user interface with i18n support
 
-use crate::keygen::{generate_lkp, generate_spk, validate_tskey};
-use crate::types::{LicenseInfo, SPKCurve, LICENSE_TYPES};
+use lyssa_rds_gen::keygen::{
+    diagnose_tskey, generate_lkp, generate_spk, get_spkid, normalize_pid, validate_pid,
+};
+use lyssa_rds_gen::types::{GenerationResult, KeyKind, LKPCurve, LicenseInfo, SPKCurve, LICENSE_TYPES};
 use eframe::egui;
-use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use std::sync::{mpsc, OnceLock};
+use std::time::{Duration, Instant};
 
 #[derive(Clone, Copy, PartialEq)]
 enum Language {
     English,
     Chinese,
+    Japanese,
 }
 
+impl Language {
+    /// Every language in the order the language button cycles through them
+    const ALL: [Language; 3] = [Language::English, Language::Chinese, Language::Japanese];
+
+    /// Embedded localization file for this language
+    fn source(self) -> &'static str {
+        match self {
+            Language::English => include_str!("../locales/en.json"),
+            Language::Chinese => include_str!("../locales/zh.json"),
+            Language::Japanese => include_str!("../locales/ja.json"),
+        }
+    }
+
+    fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|&l| l == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+}
+
+/// Localized UI strings, loaded from `locales/<lang>.json`
+#[derive(Deserialize)]
 struct UiText {
+    /// Shown on the language button while this language is active
+    language_name: &'static str,
+    /// Start of localized error messages, used to style the status box
+    error_prefix: &'static str,
     title: &'static str,
     subtitle: &'static str,
     product_id: &'static str,
     product_id_hint: &'static str,
+    pid_valid: &'static str,
+    spkid_warning: &'static str,
     existing_spk: &'static str,
     existing_spk_hint: &'static str,
+    existing_lkp: &'static str,
+    existing_lkp_hint: &'static str,
     license_count: &'static str,
     license_type: &'static str,
+    license_filter_hint: &'static str,
+    license_no_match: &'static str,
     generate_spk: &'static str,
     validate_spk: &'static str,
+    validate_lkp: &'static str,
     generate_lkp: &'static str,
     generated_keys: &'static str,
     spk_label: &'static str,
     lkp_label: &'static str,
     copy: &'static str,
+    copy_all: &'static str,
+    save: &'static str,
+    #[cfg_attr(not(feature = "qr"), allow(dead_code))]
+    show_qr: &'static str,
+    /// Followed by the path the keys were written to
+    keys_saved: &'static str,
+    #[allow(dead_code)]
     status: &'static str,
     input_params: &'static str,
     error_pid_required: &'static str,
     error_spk_required: &'static str,
+    error_lkp_required: &'static str,
     error_count_range: &'static str,
     generating_spk: &'static str,
     generating_lkp: &'static str,
@@ -38,98 +83,384 @@ struct UiText {
     spk_generated: &'static str,
     spk_validated: &'static str,
     spk_invalid: &'static str,
+    lkp_validated: &'static str,
+    lkp_invalid: &'static str,
     lkp_generated: &'static str,
+    history: &'static str,
+    clear_history: &'static str,
+    /// Tab label for a session without a PID, followed by its number
+    new_session: &'static str,
+    close_session: &'static str,
+    /// Helper text under an empty PID field
+    pid_required_hint: &'static str,
+    /// Tooltip of the disabled "Validate SPK" button while no SPK is entered
+    spk_required_hint: &'static str,
+    /// Tooltip of the disabled "Validate LKP" button while no LKP is entered
+    lkp_required_hint: &'static str,
+    /// Helper text under a count outside 1..=9999
+    count_range_hint: &'static str,
 }
 
 impl UiText {
-    fn get(lang: Language) -> Self {
-        match lang {
-            Language::English => Self {
-                title: "🔑 LyssaRDSGen",
-                subtitle: "RDS License Key Generator",
-                product_id: "Product ID",
-                product_id_hint: "e.g., 00490-92005-99454-AT527",
-                existing_spk: "Existing SPK (Optional)",
-                existing_spk_hint: "Leave empty to generate new",
-                license_count: "License Count",
-                license_type: "License Type",
-                generate_spk: "🔐 Generate SPK",
-                validate_spk: "✓ Validate SPK",
-                generate_lkp: "📦 Generate LKP",
-                generated_keys: "✨ Generated Keys",
-                spk_label: "License Server ID (SPK)",
-                lkp_label: "License Key Pack (LKP)",
-                copy: "📋 Copy",
-                status: "Status",
-                input_params: "📝 Input Parameters",
-                error_pid_required: "Error: PID is required",
-                error_spk_required: "Error: SPK is required for validation",
-                error_count_range: "Error: Count must be between 1 and 9999",
-                generating_spk: "Generating SPK...",
-                generating_lkp: "Generating LKP...",
-                validating_spk: "Validating SPK...",
-                spk_generated: "SPK generated successfully!",
-                spk_validated: "SPK validation successful!",
-                spk_invalid: "Error: SPK does not match the PID",
-                lkp_generated: "LKP generated successfully!",
-            },
-            Language::Chinese => Self {
-                title: "🔑 LyssaRDSGen",
-                subtitle: "RDS 许可证密钥生成器",
-                product_id: "产品 ID",
-                product_id_hint: "例如：00490-92005-99454-AT527",
-                existing_spk: "现有 SPK（可选）",
-                existing_spk_hint: "留空以生成新密钥",
-                license_count: "许可证数量",
-                license_type: "许可证类型",
-                generate_spk: "🔐 生成 SPK",
-                validate_spk: "✓ 验证 SPK",
-                generate_lkp: "📦 生成 LKP",
-                generated_keys: "✨ 生成的密钥",
-                spk_label: "许可证服务器 ID (SPK)",
-                lkp_label: "许可证密钥包 (LKP)",
-                copy: "📋 复制",
-                status: "状态",
-                input_params: "📝 输入参数",
-                error_pid_required: "错误：需要产品 ID",
-                error_spk_required: "错误：验证需要 SPK",
-                error_count_range: "错误：数量必须在 1 到 9999 之间",
-                generating_spk: "正在生成 SPK...",
-                generating_lkp: "正在生成 LKP...",
-                validating_spk: "正在验证 SPK...",
-                spk_generated: "SPK 生成成功！",
-                spk_validated: "SPK 验证成功！",
-                spk_invalid: "错误：SPK 与 PID 不匹配",
-                lkp_generated: "LKP 生成成功！",
-            },
+    fn get(lang: Language) -> &'static Self {
+        static TEXTS: OnceLock<Vec<UiText>> = OnceLock::new();
+        let texts = TEXTS.get_or_init(|| {
+            Language::ALL
+                .iter()
+                .map(|lang| serde_json::from_str(lang.source()).expect("valid localization file"))
+                .collect()
+        });
+        &texts[Language::ALL.iter().position(|&l| l == lang).unwrap_or(0)]
+    }
+}
+
+/// Font covering Chinese and Japanese text
+const CJK_FONT_FILE: &str = "NotoSansCJK-VF.ttc";
+
+/// CJK font embedded in the binary (`cjk-font` feature)
+#[cfg(feature = "cjk-font")]
+fn load_cjk_font() -> Option<egui::FontData> {
+    Some(egui::FontData::from_static(include_bytes!("../fonts/NotoSansCJK-VF.ttc")))
+}
+
+/// CJK font read at runtime from `fonts/` next to the executable or in the working directory
+#[cfg(not(feature = "cjk-font"))]
+fn load_cjk_font() -> Option<egui::FontData> {
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()));
+    exe_dir
+        .into_iter()
+        .chain(std::iter::once(std::path::PathBuf::from(".")))
+        .map(|dir| dir.join("fonts").join(CJK_FONT_FILE))
+        .find_map(|path| std::fs::read(path).ok())
+        .map(egui::FontData::from_owned)
+}
+
+/// Window and taskbar icon, decoded at startup
+const APP_ICON: &[u8] = include_bytes!("../assets/icon.png");
+
+/// eframe storage key for the persisted history
+const HISTORY_KEY: &str = "history";
+/// Older history entries are dropped beyond this many
+const MAX_HISTORY: usize = 50;
+
+/// eframe storage key for the persisted theme choice
+const DARK_MODE_KEY: &str = "dark_mode";
+
+/// Colors used by the custom cards and labels, per theme
+#[derive(Clone, Copy)]
+struct Palette {
+    card_fill: egui::Color32,
+    card_stroke: egui::Color32,
+    shadow: egui::Color32,
+    heading: egui::Color32,
+    label: egui::Color32,
+    muted: egui::Color32,
+    footer: egui::Color32,
+    accent: egui::Color32,
+    widget_fill: egui::Color32,
+    widget_weak_fill: egui::Color32,
+    chip_fill: egui::Color32,
+    chip_stroke: egui::Color32,
+    key_fill: egui::Color32,
+    success_fill: egui::Color32,
+    success_stroke: egui::Color32,
+    success_heading: egui::Color32,
+    success_text: egui::Color32,
+    error_fill: egui::Color32,
+    error_stroke: egui::Color32,
+    error_text: egui::Color32,
+    warning_text: egui::Color32,
+}
+
+impl Palette {
+    fn light() -> Self {
+        use egui::Color32;
+        Self {
+            card_fill: Color32::from_rgb(255, 255, 255),
+            card_stroke: Color32::from_rgb(229, 231, 235),
+            shadow: Color32::from_rgba_premultiplied(0, 0, 0, 10),
+            heading: Color32::from_rgb(31, 41, 55),
+            label: Color32::from_rgb(75, 85, 99),
+            muted: Color32::from_rgb(107, 114, 128),
+            footer: Color32::from_rgb(156, 163, 175),
+            accent: Color32::from_rgb(59, 130, 246),
+            widget_fill: Color32::from_rgb(245, 247, 250),
+            widget_weak_fill: Color32::from_rgb(250, 251, 252),
+            chip_fill: Color32::from_rgb(243, 244, 246),
+            chip_stroke: Color32::from_rgb(209, 213, 219),
+            key_fill: Color32::WHITE,
+            success_fill: Color32::from_rgb(240, 253, 244),
+            success_stroke: Color32::from_rgb(167, 243, 208),
+            success_heading: Color32::from_rgb(6, 78, 59),
+            success_text: Color32::from_rgb(22, 101, 52),
+            error_fill: Color32::from_rgb(254, 242, 242),
+            error_stroke: Color32::from_rgb(252, 165, 165),
+            error_text: Color32::from_rgb(153, 27, 27),
+            warning_text: Color32::from_rgb(146, 64, 14),
+        }
+    }
+
+    fn dark() -> Self {
+        use egui::Color32;
+        Self {
+            card_fill: Color32::from_rgb(31, 41, 55),
+            card_stroke: Color32::from_rgb(55, 65, 81),
+            shadow: Color32::from_rgba_premultiplied(0, 0, 0, 60),
+            heading: Color32::from_rgb(243, 244, 246),
+            label: Color32::from_rgb(209, 213, 219),
+            muted: Color32::from_rgb(156, 163, 175),
+            footer: Color32::from_rgb(107, 114, 128),
+            accent: Color32::from_rgb(96, 165, 250),
+            widget_fill: Color32::from_rgb(55, 65, 81),
+            widget_weak_fill: Color32::from_rgb(17, 24, 39),
+            chip_fill: Color32::from_rgb(55, 65, 81),
+            chip_stroke: Color32::from_rgb(75, 85, 99),
+            key_fill: Color32::from_rgb(17, 24, 39),
+            success_fill: Color32::from_rgb(6, 46, 33),
+            success_stroke: Color32::from_rgb(21, 128, 61),
+            success_heading: Color32::from_rgb(167, 243, 208),
+            success_text: Color32::from_rgb(134, 239, 172),
+            error_fill: Color32::from_rgb(69, 10, 10),
+            error_stroke: Color32::from_rgb(185, 28, 28),
+            error_text: Color32::from_rgb(252, 165, 165),
+            warning_text: Color32::from_rgb(252, 211, 77),
+        }
+    }
+
+    fn get(dark_mode: bool) -> Self {
+        if dark_mode {
+            Self::dark()
+        } else {
+            Self::light()
         }
     }
 }
 
-pub struct LyssaRDSGenApp {
+/// A previous generation, kept for the rest of the session and across restarts
+#[derive(Serialize, Deserialize)]
+struct HistoryEntry {
+    timestamp: String,
+    result: GenerationResult,
+}
+
+impl HistoryEntry {
+    fn new(result: GenerationResult) -> Self {
+        Self {
+            timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            result,
+        }
+    }
+
+    /// The generated keys with their kind, SPK first
+    fn keys(&self) -> impl Iterator<Item = (KeyKind, &str)> {
+        let spk = self.result.spk.as_deref().map(|spk| (KeyKind::Spk, spk));
+        let lkp = self.result.lkp.as_deref().map(|lkp| (KeyKind::Lkp, lkp));
+        spk.into_iter().chain(lkp)
+    }
+}
+
+/// Append `entry`, dropping the oldest entries beyond `MAX_HISTORY`
+fn push_history(history: &mut Vec<HistoryEntry>, entry: HistoryEntry) {
+    history.push(entry);
+    let excess = history.len().saturating_sub(MAX_HISTORY);
+    history.drain(..excess);
+}
+
+/// Draw `key` as a black-on-white QR code with the standard 4-module quiet zone
+///
+/// The code is rebuilt every frame, so it always matches the key shown above it.
+#[cfg(feature = "qr")]
+fn qr_ui(ui: &mut egui::Ui, key: &str) {
+    const MODULE_SIZE: f32 = 4.0;
+    const QUIET_ZONE: usize = 4;
+
+    let Ok(code) = qrcode::QrCode::new(key.as_bytes()) else {
+        return;
+    };
+    let width = code.width();
+    let side = (width + 2 * QUIET_ZONE) as f32 * MODULE_SIZE;
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(side, side), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, egui::Color32::WHITE);
+    for (idx, color) in code.to_colors().iter().enumerate() {
+        if *color == qrcode::Color::Dark {
+            let (x, y) = (idx % width + QUIET_ZONE, idx / width + QUIET_ZONE);
+            let min = rect.min + egui::vec2(x as f32, y as f32) * MODULE_SIZE;
+            painter.rect_filled(
+                egui::Rect::from_min_size(min, egui::vec2(MODULE_SIZE, MODULE_SIZE)),
+                0.0,
+                egui::Color32::BLACK,
+            );
+        }
+    }
+}
+
+const GENERATE_SPK_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::G);
+const GENERATE_LKP_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::L);
+const VALIDATE_SPK_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::K);
+/// Only copies all keys while no text field has focus
+const COPY_ALL_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::C);
+
+/// How long the PID must stay unchanged before the SPKID preview is recomputed
+const PID_PREVIEW_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Result of checking a PID as it is typed
+enum PidCheck {
+    Valid(u64),
+    /// No SPKID can be extracted, so generation will fail
+    SpkidUnreadable(String),
+    /// The SPKID parses but the PID layout is wrong
+    Invalid(String),
+}
+
+/// SPKID (or validation error) computed for a normalized PID
+struct PidPreview {
+    pid: String,
+    check: PidCheck,
+}
+
+impl PidPreview {
+    fn new(pid: String) -> Self {
+        let check = match (get_spkid(&pid), validate_pid(&pid)) {
+            (Err(e), _) => PidCheck::SpkidUnreadable(e.to_string()),
+            (Ok(_), Err(e)) => PidCheck::Invalid(e.to_string()),
+            (Ok(spkid), Ok(())) => PidCheck::Valid(spkid),
+        };
+        Self { pid, check }
+    }
+}
+
+/// What a worker thread sends back once generation finishes
+enum JobResult {
+    Spk(anyhow::Result<String>),
+    Lkp(anyhow::Result<String>, LicenseInfo),
+}
+
+/// Key generation running on a worker thread for one session
+struct Job {
+    pid: String,
+    receiver: mpsc::Receiver<JobResult>,
+}
+
+impl Job {
+    /// Run `work` on a new thread, repainting `ctx` when it is done
+    fn spawn(ctx: &egui::Context, pid: String, work: impl FnOnce() -> JobResult + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            // The session may have been closed meanwhile, dropping the receiver
+            let _ = sender.send(work());
+            ctx.request_repaint();
+        });
+        Self { pid, receiver }
+    }
+}
+
+/// Inputs and generated keys for one PID, shown as a tab
+struct Session {
     pid: String,
+    pid_preview: Option<PidPreview>,
+    /// Last PID edit not yet reflected in `pid_preview`
+    pid_edited_at: Option<Instant>,
     spk: String,
-    count: u32,
+    /// LKP entered for validation
+    lkp: String,
+    /// Count field as typed, so out-of-range input stays visible
+    count_text: String,
     selected_license: usize,
     generated_spk: String,
     generated_lkp: String,
+    /// License description of `generated_lkp`
+    generated_lkp_description: String,
     status_message: String,
-    is_generating: bool,
-    language: Language,
+    /// Generation in progress for this session, if any
+    job: Option<Job>,
 }
 
-impl Default for LyssaRDSGenApp {
+impl Default for Session {
     fn default() -> Self {
         Self {
             pid: String::new(),
+            pid_preview: None,
+            pid_edited_at: None,
             spk: String::new(),
-            count: 1,
+            lkp: String::new(),
+            count_text: "1".to_string(),
             selected_license: 18, // Default to Windows Server 2022 Per Device
             generated_spk: String::new(),
             generated_lkp: String::new(),
+            generated_lkp_description: String::new(),
             status_message: String::new(),
-            is_generating: false,
+            job: None,
+        }
+    }
+}
+
+impl Session {
+    /// Tab label: the PID once one is entered, otherwise a numbered placeholder
+    fn title(&self, index: usize, text: &UiText) -> String {
+        let pid = normalize_pid(&self.pid);
+        if pid.is_empty() {
+            format!("{} {}", text.new_session, index + 1)
+        } else {
+            pid
+        }
+    }
+
+    /// License count, if the count field holds a number in 1..=9999
+    fn count(&self) -> Option<u32> {
+        self.count_text.trim().parse().ok().filter(|count| (1..=9999).contains(count))
+    }
+
+    fn is_generating(&self) -> bool {
+        self.job.is_some()
+    }
+
+    /// Whether the PID is entered and passes the checks behind the live preview
+    fn pid_is_valid(&self) -> bool {
+        let pid = normalize_pid(&self.pid);
+        !pid.is_empty() && matches!(PidPreview::new(pid).check, PidCheck::Valid(_))
+    }
+
+    /// Generated keys as a labeled block for "Copy All"
+    fn key_block(&self) -> String {
+        let description = if self.generated_lkp.is_empty() {
+            ""
+        } else {
+            &self.generated_lkp_description
+        };
+        crate::ui::format_key_block(&self.generated_spk, &self.generated_lkp, description)
+    }
+}
+
+pub struct LyssaRDSGenApp {
+    /// Always holds at least one session
+    sessions: Vec<Session>,
+    /// Index of the session shown and acted on
+    active: usize,
+    language: Language,
+    dark_mode: bool,
+    /// Draw the generated keys as QR codes under the output card
+    #[cfg_attr(not(feature = "qr"), allow(dead_code))]
+    show_qr: bool,
+    history: Vec<HistoryEntry>,
+}
+
+impl Default for LyssaRDSGenApp {
+    fn default() -> Self {
+        Self {
+            sessions: vec![Session::default()],
+            active: 0,
             language: Language::Chinese,
+            dark_mode: false,
+            show_qr: false,
+            history: Vec::new(),
         }
     }
 }
@@ -139,151 +470,353 @@ impl LyssaRDSGenApp {
         // Configure fonts to support Chinese characters
         let mut fonts = egui::FontDefinitions::default();
         
-        // Add Noto Sans CJK font for Chinese support
-        fonts.font_data.insert(
-            "noto_sans_cjk".to_owned(),
-            egui::FontData::from_static(include_bytes!("../fonts/NotoSansCJK-VF.ttc")),
-        );
-        
-        // Put the Chinese font first in the list so it's used for Chinese characters
-        fonts
-            .families
-            .entry(egui::FontFamily::Proportional)
-            .or_default()
-            .insert(0, "noto_sans_cjk".to_owned());
-        
-        fonts
-            .families
-            .entry(egui::FontFamily::Monospace)
-            .or_default()
-            .insert(0, "noto_sans_cjk".to_owned());
+        // Add Noto Sans CJK font for Chinese support, keeping egui's defaults if it is missing
+        match load_cjk_font() {
+            Some(font) => {
+                fonts.font_data.insert("noto_sans_cjk".to_owned(), font);
+                
+                // Put the Chinese font first in the list so it's used for Chinese characters
+                fonts
+                    .families
+                    .entry(egui::FontFamily::Proportional)
+                    .or_default()
+                    .insert(0, "noto_sans_cjk".to_owned());
+                
+                fonts
+                    .families
+                    .entry(egui::FontFamily::Monospace)
+                    .or_default()
+                    .insert(0, "noto_sans_cjk".to_owned());
+            }
+            None => eprintln!(
+                "Warning: {} not found; Chinese and Japanese text will not render. \
+                 Build with --features cjk-font or place it in a fonts/ directory next to the executable.",
+                CJK_FONT_FILE
+            ),
+        }
         
         cc.egui_ctx.set_fonts(fonts);
         
-        Self::default()
+        let mut history: Vec<HistoryEntry> = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, HISTORY_KEY))
+            .unwrap_or_default();
+        history.drain(..history.len().saturating_sub(MAX_HISTORY));
+        let dark_mode = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, DARK_MODE_KEY))
+            .unwrap_or_default();
+        
+        Self {
+            history,
+            dark_mode,
+            ..Self::default()
+        }
     }
 
-    fn generate_spk_clicked(&mut self, text: &UiText) {
-        if self.pid.trim().is_empty() {
-            self.status_message = text.error_pid_required.to_string();
+    fn active_session(&self) -> &Session {
+        &self.sessions[self.active]
+    }
+
+    fn add_session(&mut self) {
+        self.sessions.push(Session::default());
+        self.active = self.sessions.len() - 1;
+    }
+
+    /// Close a tab, keeping at least one session open
+    fn close_session(&mut self, index: usize) {
+        if self.sessions.len() <= 1 {
+            self.sessions[0] = Session::default();
             return;
         }
+        self.sessions.remove(index);
+        if self.active > index || self.active == self.sessions.len() {
+            self.active -= 1;
+        }
+    }
 
-        self.is_generating = true;
-        self.status_message = text.generating_spk.to_string();
-
-        match generate_spk(&self.pid) {
-            Ok(spk) => {
-                self.generated_spk = spk;
-                self.status_message = text.spk_generated.to_string();
-            }
-            Err(e) => {
-                self.status_message = format!("Error: {}", e);
-            }
+    fn generate_spk_clicked(&mut self, ctx: &egui::Context, text: &UiText) {
+        let session = &mut self.sessions[self.active];
+        if session.pid.trim().is_empty() {
+            session.status_message = text.error_pid_required.to_string();
+            return;
         }
 
-        self.is_generating = false;
+        session.status_message = text.generating_spk.to_string();
+
+        let pid = normalize_pid(&session.pid);
+        let worker_pid = pid.clone();
+        session.job = Some(Job::spawn(ctx, pid, move || JobResult::Spk(generate_spk(&worker_pid))));
     }
 
     fn validate_spk_clicked(&mut self, text: &UiText) {
-        if self.pid.trim().is_empty() {
-            self.status_message = text.error_pid_required.to_string();
+        let session = &mut self.sessions[self.active];
+        if session.pid.trim().is_empty() {
+            session.status_message = text.error_pid_required.to_string();
             return;
         }
 
-        if self.spk.trim().is_empty() {
-            self.status_message = text.error_spk_required.to_string();
+        if session.spk.trim().is_empty() {
+            session.status_message = text.error_spk_required.to_string();
             return;
         }
 
-        self.is_generating = true;
-        self.status_message = text.validating_spk.to_string();
-
-        match validate_tskey(
-            &self.pid,
-            &self.spk,
-            SPKCurve::gx(),
-            SPKCurve::gy(),
-            SPKCurve::kx(),
-            SPKCurve::ky(),
-            BigUint::from(SPKCurve::A),
-            SPKCurve::p(),
-            true,
-        ) {
-            Ok(true) => {
-                self.status_message = text.spk_validated.to_string();
-            }
-            Ok(false) => {
-                self.status_message = text.spk_invalid.to_string();
-            }
-            Err(e) => {
-                self.status_message = format!("Error: {}", e);
-            }
-        }
+        session.status_message = text.validating_spk.to_string();
 
-        self.is_generating = false;
+        let diagnosis = diagnose_tskey(&normalize_pid(&session.pid), &session.spk, &SPKCurve::params(), KeyKind::Spk);
+        session.status_message = crate::ui::validation_status(diagnosis, text.spk_validated, text.spk_invalid);
     }
 
-    fn generate_lkp_clicked(&mut self, text: &UiText) {
-        if self.pid.trim().is_empty() {
-            self.status_message = text.error_pid_required.to_string();
+    fn validate_lkp_clicked(&mut self, text: &UiText) {
+        let session = &mut self.sessions[self.active];
+        if session.pid.trim().is_empty() {
+            session.status_message = text.error_pid_required.to_string();
+            return;
+        }
+
+        if session.lkp.trim().is_empty() {
+            session.status_message = text.error_lkp_required.to_string();
             return;
         }
 
-        let count = self.count;
-        if !(1..=9999).contains(&count) {
-            self.status_message = text.error_count_range.to_string();
+        let diagnosis = diagnose_tskey(&normalize_pid(&session.pid), &session.lkp, &LKPCurve::params(), KeyKind::Lkp);
+        session.status_message = crate::ui::validation_status(diagnosis, text.lkp_validated, text.lkp_invalid);
+    }
+
+    fn generate_lkp_clicked(&mut self, ctx: &egui::Context, text: &UiText) {
+        let session = &mut self.sessions[self.active];
+        if session.pid.trim().is_empty() {
+            session.status_message = text.error_pid_required.to_string();
             return;
         }
 
-        let license_type = LICENSE_TYPES[self.selected_license].0;
+        let Some(count) = session.count() else {
+            session.status_message = text.error_count_range.to_string();
+            return;
+        };
+
+        let license_type = LICENSE_TYPES[session.selected_license].0;
         let license_info = match LicenseInfo::parse(license_type) {
             Ok(info) => info,
             Err(e) => {
-                self.status_message = format!("Error: {}", e);
+                session.status_message = format!("Error: {}", e);
                 return;
             }
         };
 
-        self.is_generating = true;
-        self.status_message = text.generating_lkp.to_string();
-
-        match generate_lkp(
-            &self.pid,
-            count,
-            license_info.chid,
-            license_info.major_ver,
-            license_info.minor_ver,
-        ) {
-            Ok(lkp) => {
-                self.generated_lkp = lkp;
-                self.status_message = format!(
-                    "{} ({})",
-                    text.lkp_generated,
-                    license_info.description
-                );
+        session.status_message = text.generating_lkp.to_string();
+
+        let pid = normalize_pid(&session.pid);
+        let worker_pid = pid.clone();
+        session.job = Some(Job::spawn(ctx, pid, move || {
+            let lkp = generate_lkp(
+                &worker_pid,
+                count,
+                license_info.chid,
+                license_info.major_ver,
+                license_info.minor_ver,
+            );
+            JobResult::Lkp(lkp, license_info)
+        }));
+    }
+
+    /// Collect results from finished workers into their sessions and the history
+    fn poll_jobs(&mut self, text: &UiText) {
+        for session in &mut self.sessions {
+            let Some(job) = &session.job else {
+                continue;
+            };
+            let result = match job.receiver.try_recv() {
+                Ok(result) => result,
+                Err(mpsc::TryRecvError::Empty) => continue,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    session.job = None;
+                    session.status_message = "Error: generation stopped unexpectedly".to_string();
+                    continue;
+                }
+            };
+            let pid = session.job.take().map(|job| job.pid).unwrap_or_default();
+            match result {
+                JobResult::Spk(Ok(spk)) => {
+                    push_history(
+                        &mut self.history,
+                        HistoryEntry::new(GenerationResult {
+                            pid,
+                            spk: Some(spk.clone()),
+                            lkp: None,
+                            license: None,
+                        }),
+                    );
+                    session.generated_spk = spk;
+                    session.status_message = text.spk_generated.to_string();
+                }
+                JobResult::Lkp(Ok(lkp), license_info) => {
+                    session.generated_lkp = lkp.clone();
+                    session.status_message = format!(
+                        "{} ({})",
+                        text.lkp_generated,
+                        license_info.description
+                    );
+                    session.generated_lkp_description = license_info.description.clone();
+                    push_history(
+                        &mut self.history,
+                        HistoryEntry::new(GenerationResult {
+                            pid,
+                            spk: None,
+                            lkp: Some(lkp),
+                            license: Some(license_info),
+                        }),
+                    );
+                }
+                JobResult::Spk(Err(e)) | JobResult::Lkp(Err(e), _) => {
+                    session.status_message = format!("Error: {}", e);
+                }
             }
-            Err(e) => {
-                self.status_message = format!("Error: {}", e);
+        }
+    }
+
+    /// Ask for a file and write the PID and generated keys to it
+    fn save_keys_clicked(&mut self, text: &UiText) {
+        let session = &mut self.sessions[self.active];
+        let pid = normalize_pid(&session.pid);
+        let file_name = if pid.is_empty() { "keys.txt".to_string() } else { format!("{}.txt", pid) };
+        let Some(path) = rfd::FileDialog::new()
+            .set_file_name(file_name)
+            .add_filter("Text", &["txt"])
+            .save_file()
+        else {
+            return;
+        };
+
+        match std::fs::write(&path, format!("PID: {}\n{}", pid, session.key_block())) {
+            Ok(()) => session.status_message = format!("{} {}", text.keys_saved, path.display()),
+            Err(e) => session.status_message = format!("Error: {}", e),
+        }
+    }
+
+    /// Tab bar to switch, add and close sessions
+    fn session_tabs_ui(&mut self, ui: &mut egui::Ui, text: &UiText) {
+        let pal = Palette::get(self.dark_mode);
+        let mut close = None;
+        ui.horizontal_wrapped(|ui| {
+            for (idx, session) in self.sessions.iter().enumerate() {
+                let title = egui::RichText::new(session.title(idx, text)).size(14.0);
+                if ui.selectable_label(idx == self.active, title).clicked() {
+                    self.active = idx;
+                }
+                if ui
+                    .small_button(egui::RichText::new("×").color(pal.muted))
+                    .on_hover_text(text.close_session)
+                    .clicked()
+                {
+                    close = Some(idx);
+                }
+                ui.add_space(6.0);
+            }
+            if ui
+                .add(
+                    egui::Button::new(egui::RichText::new("+").size(14.0))
+                        .fill(pal.chip_fill)
+                        .stroke(egui::Stroke::new(1.0, pal.chip_stroke)),
+                )
+                .on_hover_text(text.new_session)
+                .clicked()
+            {
+                self.add_session();
             }
+        });
+        if let Some(idx) = close {
+            self.close_session(idx);
         }
+    }
+
+    fn history_ui(&mut self, ui: &mut egui::Ui, text: &UiText) {
+        let pal = Palette::get(self.dark_mode);
+        egui::Frame::none()
+            .fill(pal.card_fill)
+            .stroke(egui::Stroke::new(
+                1.0,
+                pal.card_stroke,
+            ))
+            .rounding(egui::Rounding::same(12.0))
+            .inner_margin(egui::Margin::same(20.0))
+            .show(ui, |ui| {
+                let id = ui.make_persistent_id("history_collapsed");
+                egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, true)
+                    .show_header(ui, |ui| {
+                        ui.label(
+                            egui::RichText::new(text.history)
+                                .size(18.0)
+                                .strong()
+                                .color(pal.heading),
+                        );
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.button(text.clear_history).clicked() {
+                                self.history.clear();
+                            }
+                        });
+                    })
+                    .body(|ui| {
+                        ui.add_space(10.0);
 
-        self.is_generating = false;
+                        egui::ScrollArea::vertical()
+                            .id_source("history")
+                            .max_height(220.0)
+                            .show(ui, |ui| {
+                                for entry in self.history.iter().rev() {
+                                    for (kind, key) in entry.keys() {
+                                        let mut heading =
+                                            format!("{}  {}  {}", entry.timestamp, kind.name(), entry.result.pid);
+                                        if let (KeyKind::Lkp, Some(license)) = (kind, &entry.result.license) {
+                                            heading.push_str(&format!("  ({})", license.description));
+                                        }
+                                        ui.label(
+                                            egui::RichText::new(heading)
+                                                .size(12.0)
+                                                .color(pal.muted),
+                                        );
+                                        ui.horizontal(|ui| {
+                                            ui.label(
+                                                egui::RichText::new(key)
+                                                    .size(13.0)
+                                                    .family(egui::FontFamily::Monospace),
+                                            );
+                                            if ui.small_button(text.copy).clicked() {
+                                                ui.output_mut(|o| o.copied_text = key.to_string());
+                                            }
+                                        });
+                                        ui.add_space(6.0);
+                                    }
+                                }
+                            });
+                    });
+            });
     }
 }
 
 impl eframe::App for LyssaRDSGenApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, HISTORY_KEY, &self.history);
+        eframe::set_value(storage, DARK_MODE_KEY, &self.dark_mode);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         let text = UiText::get(self.language);
+        let pal = Palette::get(self.dark_mode);
 
-        // Apply custom styling
+        // Apply custom styling on top of the base theme
         let mut style = (*ctx.style()).clone();
+        style.visuals = if self.dark_mode {
+            egui::Visuals::dark()
+        } else {
+            egui::Visuals::light()
+        };
         style.spacing.item_spacing = egui::vec2(10.0, 8.0);
         style.spacing.button_padding = egui::vec2(16.0, 8.0);
         style.spacing.window_margin = egui::Margin::same(15.0);
         style.visuals.widgets.noninteractive.bg_stroke.width = 1.0;
-        style.visuals.widgets.inactive.bg_fill = egui::Color32::from_rgb(245, 247, 250);
-        style.visuals.widgets.inactive.weak_bg_fill = egui::Color32::from_rgb(250, 251, 252);
+        style.visuals.widgets.inactive.bg_fill = pal.widget_fill;
+        style.visuals.widgets.inactive.weak_bg_fill = pal.widget_weak_fill;
         style.visuals.widgets.active.bg_fill = egui::Color32::from_rgb(59, 130, 246);
         style.visuals.widgets.hovered.bg_fill = egui::Color32::from_rgb(96, 165, 250);
         style.visuals.window_rounding = egui::Rounding::same(12.0);
@@ -293,6 +826,23 @@ impl eframe::App for LyssaRDSGenApp {
         style.visuals.widgets.active.rounding = egui::Rounding::same(8.0);
         ctx.set_style(style);
 
+        self.poll_jobs(text);
+
+        // Keyboard accelerators, applied through the same guards as the buttons
+        let (spk_shortcut, lkp_shortcut, validate_shortcut) = ctx.input_mut(|i| {
+            (
+                i.consume_shortcut(&GENERATE_SPK_SHORTCUT),
+                i.consume_shortcut(&GENERATE_LKP_SHORTCUT),
+                i.consume_shortcut(&VALIDATE_SPK_SHORTCUT),
+            )
+        });
+        let copy_shortcut = ctx.memory(|m| m.focused().is_none())
+            && ctx.input(|i| i.events.iter().any(|e| matches!(e, egui::Event::Copy)));
+        let session = self.active_session();
+        if copy_shortcut && !(session.generated_spk.is_empty() && session.generated_lkp.is_empty()) {
+            ctx.output_mut(|o| o.copied_text = session.key_block());
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::ScrollArea::vertical().show(ui, |ui| {
                 // Header with language switcher
@@ -301,49 +851,61 @@ impl eframe::App for LyssaRDSGenApp {
                         ui.heading(
                             egui::RichText::new(text.title)
                                 .size(32.0)
-                                .color(egui::Color32::from_rgb(59, 130, 246))
+                                .color(pal.accent)
                                 .strong(),
                         );
                         ui.label(
                             egui::RichText::new(text.subtitle)
                                 .size(16.0)
-                                .color(egui::Color32::from_rgb(107, 114, 128)),
+                                .color(pal.muted),
                         );
                     });
 
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                         // Show CURRENT language (what is selected)
-                        let lang_text = match self.language {
-                            Language::English => "🌐 English",  // Currently English, show English
-                            Language::Chinese => "🌐 中文",      // Currently Chinese, show Chinese
-                        };
+                        let lang_text = text.language_name;
                         if ui
                             .add(
                                 egui::Button::new(egui::RichText::new(lang_text).size(14.0))
-                                    .fill(egui::Color32::from_rgb(243, 244, 246))
+                                    .fill(pal.chip_fill)
                                     .stroke(egui::Stroke::new(
                                         1.0,
-                                        egui::Color32::from_rgb(209, 213, 219),
+                                        pal.chip_stroke,
                                     )),
                             )
                             .clicked()
                         {
-                            self.language = match self.language {
-                                Language::English => Language::Chinese,
-                                Language::Chinese => Language::English,
-                            };
+                            self.language = self.language.next();
+                        }
+
+                        // Theme toggle shows the theme it switches to
+                        let theme_icon = if self.dark_mode { "☀" } else { "🌙" };
+                        if ui
+                            .add(
+                                egui::Button::new(egui::RichText::new(theme_icon).size(14.0))
+                                    .fill(pal.chip_fill)
+                                    .stroke(egui::Stroke::new(1.0, pal.chip_stroke)),
+                            )
+                            .clicked()
+                        {
+                            self.dark_mode = !self.dark_mode;
                         }
                     });
                 });
 
                 ui.add_space(20.0);
 
+                // One tab per PID being worked on
+                self.session_tabs_ui(ui, text);
+
+                ui.add_space(10.0);
+
                 // Input section with card style
                 egui::Frame::none()
-                    .fill(egui::Color32::from_rgb(255, 255, 255))
+                    .fill(pal.card_fill)
                     .stroke(egui::Stroke::new(
                         1.0,
-                        egui::Color32::from_rgb(229, 231, 235),
+                        pal.card_stroke,
                     ))
                     .rounding(egui::Rounding::same(12.0))
                     .inner_margin(egui::Margin::same(20.0))
@@ -351,14 +913,15 @@ impl eframe::App for LyssaRDSGenApp {
                         offset: egui::vec2(0.0, 2.0),
                         blur: 8.0,
                         spread: 0.0,
-                        color: egui::Color32::from_rgba_premultiplied(0, 0, 0, 10),
+                        color: pal.shadow,
                     })
                     .show(ui, |ui| {
+                        let session = &mut self.sessions[self.active];
                         ui.label(
                             egui::RichText::new(text.input_params)
                                 .size(18.0)
                                 .strong()
-                                .color(egui::Color32::from_rgb(31, 41, 55)),
+                                .color(pal.heading),
                         );
                         ui.add_space(15.0);
 
@@ -366,50 +929,108 @@ impl eframe::App for LyssaRDSGenApp {
                         ui.label(
                             egui::RichText::new(text.product_id)
                                 .size(14.0)
-                                .color(egui::Color32::from_rgb(75, 85, 99)),
+                                .color(pal.label),
                         );
                         ui.add_space(5.0);
-                        ui.add_sized(
+                        let pid_response = ui.add_sized(
                             [ui.available_width(), 32.0],
-                            egui::TextEdit::singleline(&mut self.pid)
+                            egui::TextEdit::singleline(&mut session.pid)
                                 .hint_text(text.product_id_hint)
                         );
 
+                        // Recompute the SPKID preview once typing pauses
+                        if pid_response.changed() {
+                            session.pid_edited_at = Some(Instant::now());
+                        }
+                        if let Some(edited_at) = session.pid_edited_at {
+                            let elapsed = edited_at.elapsed();
+                            if elapsed >= PID_PREVIEW_DEBOUNCE {
+                                session.pid_preview = Some(PidPreview::new(normalize_pid(&session.pid)));
+                                session.pid_edited_at = None;
+                            } else {
+                                ui.ctx().request_repaint_after(PID_PREVIEW_DEBOUNCE - elapsed);
+                            }
+                        }
+
+                        // Live PID validity and SPKID preview
+                        let preview = session
+                            .pid_preview
+                            .as_ref()
+                            .filter(|preview| !preview.pid.is_empty() && preview.pid == normalize_pid(&session.pid));
+                        if let Some(preview) = preview {
+                            let (msg, color) = match &preview.check {
+                                PidCheck::Valid(spkid) => (
+                                    format!("{}  ·  SPKID: {}", text.pid_valid, spkid),
+                                    pal.success_text,
+                                ),
+                                PidCheck::SpkidUnreadable(e) => (
+                                    format!("{} {}", text.spkid_warning, e),
+                                    pal.warning_text,
+                                ),
+                                PidCheck::Invalid(e) => (
+                                    format!("✗ {}", e),
+                                    pal.error_text,
+                                ),
+                            };
+                            ui.label(egui::RichText::new(msg).size(12.0).color(color));
+                        } else if session.pid.trim().is_empty() {
+                            ui.label(
+                                egui::RichText::new(text.pid_required_hint)
+                                    .size(12.0)
+                                    .color(pal.error_text),
+                            );
+                        }
+
                         ui.add_space(12.0);
 
                         // Existing SPK
                         ui.label(
                             egui::RichText::new(text.existing_spk)
                                 .size(14.0)
-                                .color(egui::Color32::from_rgb(75, 85, 99)),
+                                .color(pal.label),
                         );
                         ui.add_space(5.0);
                         ui.add_sized(
                             [ui.available_width(), 32.0],
-                            egui::TextEdit::singleline(&mut self.spk)
+                            egui::TextEdit::singleline(&mut session.spk)
                                 .hint_text(text.existing_spk_hint)
                         );
 
                         ui.add_space(12.0);
 
+                        // Existing LKP
+                        ui.label(
+                            egui::RichText::new(text.existing_lkp)
+                                .size(14.0)
+                                .color(pal.label),
+                        );
+                        ui.add_space(5.0);
+                        ui.add_sized(
+                            [ui.available_width(), 32.0],
+                            egui::TextEdit::singleline(&mut session.lkp)
+                                .hint_text(text.existing_lkp_hint)
+                        );
+
+                        ui.add_space(12.0);
+
                         // License Count
                         ui.label(
                             egui::RichText::new(text.license_count)
                                 .size(14.0)
-                                .color(egui::Color32::from_rgb(75, 85, 99)),
+                                .color(pal.label),
                         );
                         ui.add_space(5.0);
-                        let mut count_str = self.count.to_string();
                         ui.add_sized(
                             [ui.available_width(), 32.0],
-                            egui::TextEdit::singleline(&mut count_str)
+                            egui::TextEdit::singleline(&mut session.count_text)
                                 .hint_text("1-9999")
                         );
-                        // Parse the count string back to u32
-                        if let Ok(parsed) = count_str.parse::<u32>() {
-                            if (1..=9999).contains(&parsed) {
-                                self.count = parsed;
-                            }
+                        if session.count().is_none() {
+                            ui.label(
+                                egui::RichText::new(text.count_range_hint)
+                                    .size(12.0)
+                                    .color(pal.error_text),
+                            );
                         }
 
                         ui.add_space(12.0);
@@ -418,94 +1039,175 @@ impl eframe::App for LyssaRDSGenApp {
                         ui.label(
                             egui::RichText::new(text.license_type)
                                 .size(14.0)
-                                .color(egui::Color32::from_rgb(75, 85, 99)),
+                                .color(pal.label),
                         );
                         ui.add_space(5.0);
-                        egui::ComboBox::from_id_source("license_type")
-                            .selected_text(LICENSE_TYPES[self.selected_license].1)
+                        // The filter text lives in temp memory only while the popup is open
+                        let filter_id = ui.make_persistent_id("license_filter");
+                        let combo = egui::ComboBox::from_id_source("license_type")
+                            .selected_text(LICENSE_TYPES[session.selected_license].1)
                             .width(ui.available_width())
                             .show_ui(ui, |ui| {
-                                for (idx, (_, desc)) in LICENSE_TYPES.iter().enumerate() {
+                                let stored = ui.data_mut(|d| d.get_temp::<String>(filter_id));
+                                let just_opened = stored.is_none();
+                                let mut filter = stored.unwrap_or_default();
+
+                                let filter_response = ui.add(
+                                    egui::TextEdit::singleline(&mut filter)
+                                        .hint_text(text.license_filter_hint),
+                                );
+                                if just_opened {
+                                    filter_response.request_focus();
+                                }
+
+                                let needle = filter.to_lowercase();
+                                let matches: Vec<usize> = LICENSE_TYPES
+                                    .iter()
+                                    .enumerate()
+                                    .filter(|(_, (_, desc))| desc.to_lowercase().contains(&needle))
+                                    .map(|(idx, _)| idx)
+                                    .collect();
+
+                                for &idx in &matches {
                                     ui.selectable_value(
-                                        &mut self.selected_license,
+                                        &mut session.selected_license,
                                         idx,
-                                        *desc,
+                                        LICENSE_TYPES[idx].1,
                                     );
                                 }
+                                if matches.is_empty() {
+                                    ui.label(text.license_no_match);
+                                }
+
+                                // Enter in the filter box picks the first match
+                                if filter_response.lost_focus()
+                                    && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                                {
+                                    if let Some(&first) = matches.first() {
+                                        session.selected_license = first;
+                                    }
+                                    ui.memory_mut(|m| m.close_popup());
+                                }
+
+                                ui.data_mut(|d| d.insert_temp(filter_id, filter));
                             });
+                        if combo.inner.is_none() {
+                            ui.data_mut(|d| d.remove::<String>(filter_id));
+                        }
                     });
 
                 ui.add_space(20.0);
 
-                // Action buttons with modern styling
+                // Action buttons with modern styling, greyed out until their inputs are valid
+                let session = self.active_session();
+                let generating = session.is_generating();
+                let can_generate_spk = session.pid_is_valid() && !generating;
+                let can_validate_spk = !session.pid.trim().is_empty() && !session.spk.trim().is_empty() && !generating;
+                let can_validate_lkp = !session.pid.trim().is_empty() && !session.lkp.trim().is_empty() && !generating;
+                let can_generate_lkp = can_generate_spk && session.count().is_some();
+                let (validate_hint, validate_lkp_hint) = if session.pid.trim().is_empty() {
+                    (text.pid_required_hint, text.pid_required_hint)
+                } else {
+                    (text.spk_required_hint, text.lkp_required_hint)
+                };
                 ui.horizontal(|ui| {
                     let button_height = 40.0;
 
-                    if ui
-                        .add_sized(
-                            [ui.available_width() / 3.0 - 10.0, button_height],
-                            egui::Button::new(
-                                egui::RichText::new(text.generate_spk)
-                                    .size(14.0)
-                                    .color(egui::Color32::WHITE),
+                    let button = ui
+                        .add_enabled_ui(can_generate_spk, |ui| {
+                            ui.add_sized(
+                                [ui.available_width() / 4.0 - 10.0, button_height],
+                                egui::Button::new(
+                                    egui::RichText::new(text.generate_spk)
+                                        .size(14.0)
+                                        .color(egui::Color32::WHITE),
+                                )
+                                .fill(egui::Color32::from_rgb(59, 130, 246))
+                                .stroke(egui::Stroke::NONE),
                             )
-                            .fill(egui::Color32::from_rgb(59, 130, 246))
-                            .stroke(egui::Stroke::NONE),
-                        )
-                        .clicked()
-                        && !self.is_generating
-                    {
-                        self.generate_spk_clicked(&text);
+                        })
+                        .inner
+                        .on_hover_text(ctx.format_shortcut(&GENERATE_SPK_SHORTCUT));
+                    if (button.clicked() || (spk_shortcut && can_generate_spk)) && !generating {
+                        self.generate_spk_clicked(ctx, text);
                     }
 
                     ui.add_space(5.0);
 
-                    if ui
-                        .add_sized(
-                            [ui.available_width() / 2.0 - 5.0, button_height],
-                            egui::Button::new(
-                                egui::RichText::new(text.validate_spk)
-                                    .size(14.0)
-                                    .color(egui::Color32::WHITE),
+                    let button = ui
+                        .add_enabled_ui(can_validate_spk, |ui| {
+                            ui.add_sized(
+                                [ui.available_width() / 3.0 - 7.0, button_height],
+                                egui::Button::new(
+                                    egui::RichText::new(text.validate_spk)
+                                        .size(14.0)
+                                        .color(egui::Color32::WHITE),
+                                )
+                                .fill(egui::Color32::from_rgb(16, 185, 129))
+                                .stroke(egui::Stroke::NONE),
                             )
-                            .fill(egui::Color32::from_rgb(16, 185, 129))
-                            .stroke(egui::Stroke::NONE),
-                        )
-                        .clicked()
-                        && !self.is_generating
-                    {
-                        self.validate_spk_clicked(&text);
+                        })
+                        .inner
+                        .on_hover_text(ctx.format_shortcut(&VALIDATE_SPK_SHORTCUT))
+                        .on_disabled_hover_text(validate_hint);
+                    if (button.clicked() || (validate_shortcut && can_validate_spk)) && !generating {
+                        self.validate_spk_clicked(text);
                     }
 
                     ui.add_space(5.0);
 
-                    if ui
-                        .add_sized(
-                            [ui.available_width(), button_height],
-                            egui::Button::new(
-                                egui::RichText::new(text.generate_lkp)
-                                    .size(14.0)
-                                    .color(egui::Color32::WHITE),
+                    let button = ui
+                        .add_enabled_ui(can_validate_lkp, |ui| {
+                            ui.add_sized(
+                                [ui.available_width() / 2.0 - 5.0, button_height],
+                                egui::Button::new(
+                                    egui::RichText::new(text.validate_lkp)
+                                        .size(14.0)
+                                        .color(egui::Color32::WHITE),
+                                )
+                                .fill(egui::Color32::from_rgb(13, 148, 136))
+                                .stroke(egui::Stroke::NONE),
                             )
-                            .fill(egui::Color32::from_rgb(139, 92, 246))
-                            .stroke(egui::Stroke::NONE),
-                        )
-                        .clicked()
-                        && !self.is_generating
-                    {
-                        self.generate_lkp_clicked(&text);
+                        })
+                        .inner
+                        .on_disabled_hover_text(validate_lkp_hint);
+                    if button.clicked() && !generating {
+                        self.validate_lkp_clicked(text);
+                    }
+
+                    ui.add_space(5.0);
+
+                    let button = ui
+                        .add_enabled_ui(can_generate_lkp, |ui| {
+                            ui.add_sized(
+                                [ui.available_width(), button_height],
+                                egui::Button::new(
+                                    egui::RichText::new(text.generate_lkp)
+                                        .size(14.0)
+                                        .color(egui::Color32::WHITE),
+                                )
+                                .fill(egui::Color32::from_rgb(139, 92, 246))
+                                .stroke(egui::Stroke::NONE),
+                            )
+                        })
+                        .inner
+                        .on_hover_text(ctx.format_shortcut(&GENERATE_LKP_SHORTCUT));
+                    if (button.clicked() || (lkp_shortcut && can_generate_lkp)) && !generating {
+                        self.generate_lkp_clicked(ctx, text);
                     }
                 });
 
                 ui.add_space(20.0);
 
                 // Output section with card style
-                if !self.generated_spk.is_empty() || !self.generated_lkp.is_empty() {
+                let session = self.active_session();
+                let mut save_clicked = false;
+                if !session.generated_spk.is_empty() || !session.generated_lkp.is_empty() {
                     egui::Frame::none()
-                        .fill(egui::Color32::from_rgb(240, 253, 244))
+                        .fill(pal.success_fill)
                         .stroke(egui::Stroke::new(
                             1.0,
-                            egui::Color32::from_rgb(167, 243, 208),
+                            pal.success_stroke,
                         ))
                         .rounding(egui::Rounding::same(12.0))
                         .inner_margin(egui::Margin::same(20.0))
@@ -513,39 +1215,51 @@ impl eframe::App for LyssaRDSGenApp {
                             offset: egui::vec2(0.0, 2.0),
                             blur: 8.0,
                             spread: 0.0,
-                            color: egui::Color32::from_rgba_premultiplied(0, 0, 0, 10),
+                            color: pal.shadow,
                         })
                         .show(ui, |ui| {
-                            ui.label(
-                                egui::RichText::new(text.generated_keys)
-                                    .size(18.0)
-                                    .strong()
-                                    .color(egui::Color32::from_rgb(6, 78, 59)),
-                            );
+                            ui.horizontal(|ui| {
+                                ui.label(
+                                    egui::RichText::new(text.generated_keys)
+                                        .size(18.0)
+                                        .strong()
+                                        .color(pal.success_heading),
+                                );
+                                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                    if ui
+                                        .button(text.copy_all)
+                                        .on_hover_text(ctx.format_shortcut(&COPY_ALL_SHORTCUT))
+                                        .clicked()
+                                    {
+                                        ui.output_mut(|o| o.copied_text = session.key_block());
+                                    }
+                                    save_clicked = ui.button(text.save).clicked();
+                                });
+                            });
                             ui.add_space(15.0);
 
-                            if !self.generated_spk.is_empty() {
+                            if !session.generated_spk.is_empty() {
                                 ui.label(
                                     egui::RichText::new(text.spk_label)
                                         .size(14.0)
                                         .strong()
-                                        .color(egui::Color32::from_rgb(22, 101, 52)),
+                                        .color(pal.success_text),
                                 );
                                 ui.add_space(5.0);
                                 ui.horizontal(|ui| {
                                     egui::Frame::none()
-                                        .fill(egui::Color32::WHITE)
+                                        .fill(pal.key_fill)
                                         .stroke(egui::Stroke::new(
                                             1.0,
-                                            egui::Color32::from_rgb(209, 213, 219),
+                                            pal.chip_stroke,
                                         ))
                                         .rounding(egui::Rounding::same(6.0))
                                         .inner_margin(egui::Margin::same(12.0))
                                         .show(ui, |ui| {
                                             ui.label(
-                                                egui::RichText::new(&self.generated_spk)
+                                                egui::RichText::new(&session.generated_spk)
                                                     .size(13.0)
-                                                    .color(egui::Color32::from_rgb(22, 101, 52))
+                                                    .color(pal.success_text)
                                                     .family(egui::FontFamily::Monospace),
                                             );
                                         });
@@ -553,40 +1267,40 @@ impl eframe::App for LyssaRDSGenApp {
                                         .button(
                                             egui::RichText::new(text.copy)
                                                 .size(13.0)
-                                                .color(egui::Color32::WHITE),
+                                                .color(pal.heading),
                                         )
                                         .clicked()
                                     {
                                         ui.output_mut(|o| {
-                                            o.copied_text = self.generated_spk.clone()
+                                            o.copied_text = session.generated_spk.clone()
                                         });
                                     }
                                 });
                                 ui.add_space(12.0);
                             }
 
-                            if !self.generated_lkp.is_empty() {
+                            if !session.generated_lkp.is_empty() {
                                 ui.label(
                                     egui::RichText::new(text.lkp_label)
                                         .size(14.0)
                                         .strong()
-                                        .color(egui::Color32::from_rgb(22, 101, 52)),
+                                        .color(pal.success_text),
                                 );
                                 ui.add_space(5.0);
                                 ui.horizontal(|ui| {
                                     egui::Frame::none()
-                                        .fill(egui::Color32::WHITE)
+                                        .fill(pal.key_fill)
                                         .stroke(egui::Stroke::new(
                                             1.0,
-                                            egui::Color32::from_rgb(209, 213, 219),
+                                            pal.chip_stroke,
                                         ))
                                         .rounding(egui::Rounding::same(6.0))
                                         .inner_margin(egui::Margin::same(12.0))
                                         .show(ui, |ui| {
                                             ui.label(
-                                                egui::RichText::new(&self.generated_lkp)
+                                                egui::RichText::new(&session.generated_lkp)
                                                     .size(13.0)
-                                                    .color(egui::Color32::from_rgb(22, 101, 52))
+                                                    .color(pal.success_text)
                                                     .family(egui::FontFamily::Monospace),
                                             );
                                         });
@@ -594,12 +1308,12 @@ impl eframe::App for LyssaRDSGenApp {
                                         .button(
                                             egui::RichText::new(text.copy)
                                                 .size(13.0)
-                                                .color(egui::Color32::WHITE),
+                                                .color(pal.heading),
                                         )
                                         .clicked()
                                     {
                                         ui.output_mut(|o| {
-                                            o.copied_text = self.generated_lkp.clone()
+                                            o.copied_text = session.generated_lkp.clone()
                                         });
                                     }
                                 });
@@ -607,24 +1321,56 @@ impl eframe::App for LyssaRDSGenApp {
                         });
 
                     ui.add_space(15.0);
+
+                    #[cfg(feature = "qr")]
+                    {
+                        let mut show_qr = self.show_qr;
+                        ui.checkbox(&mut show_qr, text.show_qr);
+                        if show_qr {
+                            let session = self.active_session();
+                            ui.horizontal(|ui| {
+                                for (label, key) in [(text.spk_label, &session.generated_spk), (text.lkp_label, &session.generated_lkp)] {
+                                    if !key.is_empty() {
+                                        ui.vertical(|ui| {
+                                            ui.label(egui::RichText::new(label).size(12.0).color(pal.muted));
+                                            qr_ui(ui, key);
+                                        });
+                                    }
+                                }
+                            });
+                        }
+                        self.show_qr = show_qr;
+                        ui.add_space(15.0);
+                    }
+                }
+                if save_clicked {
+                    self.save_keys_clicked(text);
+                }
+
+                // History of keys generated so far, newest first
+                if !self.history.is_empty() {
+                    self.history_ui(ui, text);
+                    ui.add_space(15.0);
                 }
 
                 // Status message with enhanced styling
-                if !self.status_message.is_empty() {
+                let generating = self.active_session().is_generating();
+                let status_message = &self.active_session().status_message;
+                if !status_message.is_empty() {
                     let (bg_color, border_color, text_color) =
-                        if self.status_message.starts_with("Error")
-                            || self.status_message.contains("错误")
+                        if status_message.starts_with("Error")
+                            || status_message.starts_with(text.error_prefix)
                         {
                             (
-                                egui::Color32::from_rgb(254, 242, 242),
-                                egui::Color32::from_rgb(252, 165, 165),
-                                egui::Color32::from_rgb(153, 27, 27),
+                                pal.error_fill,
+                                pal.error_stroke,
+                                pal.error_text,
                             )
                         } else {
                             (
-                                egui::Color32::from_rgb(240, 253, 244),
-                                egui::Color32::from_rgb(167, 243, 208),
-                                egui::Color32::from_rgb(22, 101, 52),
+                                pal.success_fill,
+                                pal.success_stroke,
+                                pal.success_text,
                             )
                         };
 
@@ -634,11 +1380,16 @@ impl eframe::App for LyssaRDSGenApp {
                         .rounding(egui::Rounding::same(8.0))
                         .inner_margin(egui::Margin::same(12.0))
                         .show(ui, |ui| {
-                            ui.label(
-                                egui::RichText::new(&self.status_message)
-                                    .size(14.0)
-                                    .color(text_color),
-                            );
+                            ui.horizontal(|ui| {
+                                if generating {
+                                    ui.spinner();
+                                }
+                                ui.label(
+                                    egui::RichText::new(status_message)
+                                        .size(14.0)
+                                        .color(text_color),
+                                );
+                            });
                         });
                 }
 
@@ -651,7 +1402,7 @@ impl eframe::App for LyssaRDSGenApp {
                     ui.label(
                         egui::RichText::new("LyssaRDSGen v1.0.0")
                             .size(12.0)
-                            .color(egui::Color32::from_rgb(156, 163, 175)),
+                            .color(pal.footer),
                     );
                 });
                 ui.add_space(10.0);
@@ -661,11 +1412,16 @@ impl eframe::App for LyssaRDSGenApp {
 }
 
 pub fn run_gui() -> Result<(), eframe::Error> {
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_inner_size([900.0, 700.0])
+        .with_min_inner_size([750.0, 600.0])
+        .with_resizable(true);
+    match eframe::icon_data::from_png_bytes(APP_ICON) {
+        Ok(icon) => viewport = viewport.with_icon(icon),
+        Err(e) => eprintln!("Failed to decode application icon: {}", e),
+    }
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([900.0, 700.0])
-            .with_min_inner_size([750.0, 600.0])
-            .with_resizable(true),
+        viewport,
         ..Default::default()
     };
 
@@ -675,3 +1431,81 @@ pub fn run_gui() -> Result<(), eframe::Error> {
         Box::new(|cc| Box::new(LyssaRDSGenApp::new(cc))),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_app_icon_decodes() {
+        let icon = eframe::icon_data::from_png_bytes(APP_ICON).unwrap();
+        assert_eq!((icon.width, icon.height), (64, 64));
+    }
+
+    #[test]
+    fn test_close_session_keeps_active_tab() {
+        let mut app = LyssaRDSGenApp::default();
+        app.add_session();
+        app.add_session();
+        for (idx, session) in app.sessions.iter_mut().enumerate() {
+            session.pid = idx.to_string();
+        }
+
+        app.active = 2;
+        app.close_session(0);
+        assert_eq!(app.active, 1);
+        assert_eq!(app.active_session().pid, "2");
+
+        app.close_session(1);
+        assert_eq!(app.active, 0);
+        assert_eq!(app.active_session().pid, "1");
+
+        // The last session is reset rather than removed
+        app.close_session(0);
+        assert_eq!(app.sessions.len(), 1);
+        assert!(app.active_session().pid.is_empty());
+    }
+
+    #[test]
+    fn test_session_field_checks() {
+        let mut session = Session::default();
+        assert!(!session.pid_is_valid());
+        session.pid = "00490-92005-99454-AT527".to_string();
+        assert!(session.pid_is_valid());
+        session.pid = "not a pid".to_string();
+        assert!(!session.pid_is_valid());
+
+        assert_eq!(session.count(), Some(1));
+        for (count_text, count) in [(" 9999 ", Some(9999)), ("0", None), ("10000", None), ("", None), ("x", None)] {
+            session.count_text = count_text.to_string();
+            assert_eq!(session.count(), count, "{:?}", count_text);
+        }
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let mut history = Vec::new();
+        for i in 0..MAX_HISTORY + 5 {
+            let result = GenerationResult {
+                pid: i.to_string(),
+                spk: Some("KEY".to_string()),
+                lkp: None,
+                license: None,
+            };
+            push_history(&mut history, HistoryEntry::new(result));
+        }
+        assert_eq!(history.len(), MAX_HISTORY);
+        assert_eq!(history[0].result.pid, "5");
+        assert_eq!(history[MAX_HISTORY - 1].result.pid, (MAX_HISTORY + 4).to_string());
+        assert_eq!(history[0].keys().collect::<Vec<_>>(), [(KeyKind::Spk, "KEY")]);
+    }
+
+    #[test]
+    fn test_localizations_load() {
+        for lang in Language::ALL {
+            let text = UiText::get(lang);
+            assert!(!text.language_name.is_empty());
+            assert!(text.error_pid_required.starts_with(text.error_prefix));
+        }
+    }
+}
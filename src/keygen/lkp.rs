@@ -1,9 +1,55 @@
 //! LKP (License Key Pack) generation
 
-use crate::crypto::bigint_to_bytes_le;
-use crate::keygen::generate_tskey;
-use crate::types::LKPCurve;
+use crate::crypto::{bigint_to_bytes_le, bytes_to_bigint_le};
+use crate::keygen::validation::decrypt_tskey;
+use crate::keygen::{generate_tskey_rfc6979, generate_tskey_with_timeout, DEFAULT_MAX_ATTEMPTS};
+use crate::types::{describe_license, CurveParams, KeyKind, LKPCurve, LicenseInfo};
 use num_bigint::BigUint;
+use std::time::Duration;
+
+/// Bit width of the CHID field at the top of the 56-bit LKP info (bits 46..56)
+const CHID_BITS: u32 = 10;
+/// Bit width of the license count field (bits 32..46)
+const COUNT_BITS: u32 = 14;
+/// Bit width of the encoded version field (bits 3..10)
+const VERSION_BITS: u32 = 7;
+/// Bit width of the minor version within the encoded version
+const MINOR_VER_BITS: u32 = 3;
+
+/// Pack CHID, count and version into the 56-bit LKP info, rejecting any value
+/// that would spill into a neighbouring field
+fn encode_lkpinfo(count: u32, chid: u32, major_ver: u32, minor_ver: u32) -> anyhow::Result<u64> {
+    if chid >= 1 << CHID_BITS {
+        anyhow::bail!("CHID {} does not fit in {} bits", chid, CHID_BITS);
+    }
+    if count >= 1 << COUNT_BITS {
+        anyhow::bail!("License count {} does not fit in {} bits", count, COUNT_BITS);
+    }
+    if minor_ver >= 1 << MINOR_VER_BITS {
+        anyhow::bail!("Minor version {} does not fit in {} bits", minor_ver, MINOR_VER_BITS);
+    }
+    // Checked before shifting, since a u32 shift silently drops the high bits
+    if major_ver >= 1 << (VERSION_BITS - MINOR_VER_BITS) {
+        anyhow::bail!(
+            "Major version {} does not fit in {} bits",
+            major_ver,
+            VERSION_BITS - MINOR_VER_BITS
+        );
+    }
+    
+    // Calculate version encoding
+    let version = if (major_ver == 5 && minor_ver > 0) || major_ver > 5 {
+        (major_ver << MINOR_VER_BITS) | minor_ver
+    } else {
+        1
+    };
+    
+    Ok(((chid as u64) << 46)
+        | ((count as u64) << 32)
+        | (2u64 << 18)
+        | (144u64 << 10)
+        | ((version as u64) << 3))
+}
 
 /// Generate LKP (License Key Pack)
 pub fn generate_lkp(
@@ -13,39 +59,238 @@ pub fn generate_lkp(
     major_ver: u32,
     minor_ver: u32,
 ) -> anyhow::Result<String> {
+    generate_lkp_with_curve(
+        pid,
+        count,
+        chid,
+        major_ver,
+        minor_ver,
+        &LKPCurve::params(),
+        DEFAULT_MAX_ATTEMPTS,
+        None,
+    )
+}
+
+/// Generate LKP (License Key Pack) signed on a custom curve, giving up after
+/// `max_attempts` or once `timeout` has passed
+#[allow(clippy::too_many_arguments)]
+pub fn generate_lkp_with_curve(
+    pid: &str,
+    count: u32,
+    chid: u32,
+    major_ver: u32,
+    minor_ver: u32,
+    curve: &CurveParams,
+    max_attempts: usize,
+    timeout: Option<Duration>,
+) -> anyhow::Result<String> {
+    let lkpdata = lkp_data(count, chid, major_ver, minor_ver)?;
+    generate_tskey_with_timeout(pid, &lkpdata, curve, KeyKind::Lkp, max_attempts, timeout, &mut rand::thread_rng())
+}
+
+/// `generate_lkp_with_curve` with deterministic nonces: the same PID, license and
+/// curve always give the same LKP (see [`generate_tskey_rfc6979`])
+#[allow(clippy::too_many_arguments)]
+pub fn generate_lkp_rfc6979(
+    pid: &str,
+    count: u32,
+    chid: u32,
+    major_ver: u32,
+    minor_ver: u32,
+    curve: &CurveParams,
+    max_attempts: usize,
+    timeout: Option<Duration>,
+) -> anyhow::Result<String> {
+    let lkpdata = lkp_data(count, chid, major_ver, minor_ver)?;
+    generate_tskey_rfc6979(pid, &lkpdata, curve, KeyKind::Lkp, max_attempts, timeout)
+}
+
+/// Generate one LKP per `(count, license)` request for `pid`, in parallel
+///
+/// The results are in the same order as `requests`. Each worker thread draws
+/// nonces from its own `rand::thread_rng()`.
+#[cfg(feature = "parallel")]
+pub fn generate_lkp_batch(pid: &str, requests: &[(u32, LicenseInfo)]) -> Vec<anyhow::Result<String>> {
+    use rayon::prelude::*;
+
+    requests
+        .par_iter()
+        .map(|(count, license)| generate_lkp(pid, *count, license.chid, license.major_ver, license.minor_ver))
+        .collect()
+}
+
+/// Generate one LKP per `(count, license)` request for `pid`, lazily
+///
+/// Each LKP is only generated when the iterator is advanced, on the calling
+/// thread, so results can be shown as they arrive and dropping the iterator
+/// skips the rest. Use `generate_lkp_batch` to spread the work over threads.
+pub fn generate_lkp_stream<'a>(
+    pid: &'a str,
+    requests: &'a [(u32, LicenseInfo)],
+) -> impl Iterator<Item = anyhow::Result<String>> + 'a {
+    requests
+        .iter()
+        .map(move |(count, license)| generate_lkp(pid, *count, license.chid, license.major_ver, license.minor_ver))
+}
+
+/// LKP info as the 7 bytes of LKP key data, after checking the count range
+fn lkp_data(count: u32, chid: u32, major_ver: u32, minor_ver: u32) -> anyhow::Result<Vec<u8>> {
     if !(1..=9999).contains(&count) {
         anyhow::bail!("License count must be between 1 and 9999");
     }
     
-    // Calculate version encoding
-    let version = if (major_ver == 5 && minor_ver > 0) || major_ver > 5 {
-        (major_ver << 3) | minor_ver
+    let lkpinfo = encode_lkpinfo(count, chid, major_ver, minor_ver)?;
+    
+    bigint_to_bytes_le(&BigUint::from(lkpinfo), 7)
+        .map_err(|_| anyhow::anyhow!("LKP Info did not convert to 7 bytes"))
+}
+
+/// Fields decoded from an LKP
+#[derive(Debug, Clone, PartialEq)]
+pub struct LkpInfo {
+    pub chid: u32,
+    pub count: u32,
+    pub major_ver: u32,
+    pub minor_ver: u32,
+    /// Encoded version field: `major << 3 | minor`, or `1` for 5.0 and older
+    pub version_raw: u32,
+    /// Description from `LICENSE_TYPES`, if the CHID and version are known
+    pub description: Option<String>,
+}
+
+/// Decrypt an LKP and decode the license it carries
+///
+/// The signature is not checked; use `validate_tskey` for that.
+pub fn inspect_lkp(pid: &str, lkp: &str) -> anyhow::Result<LkpInfo> {
+    let keydata = decrypt_tskey(pid, lkp)?;
+    let lkpinfo = bytes_to_bigint_le(&keydata[..7])
+        .to_u64_digits()
+        .first()
+        .copied()
+        .unwrap_or(0);
+    
+    let field = |shift: u32, bits: u32| ((lkpinfo >> shift) & ((1 << bits) - 1)) as u32;
+    let chid = field(46, CHID_BITS);
+    let count = field(32, COUNT_BITS);
+    let version_raw = field(3, VERSION_BITS);
+    let (major_ver, minor_ver) = if version_raw == 1 {
+        (5, 0)
     } else {
-        1
+        (version_raw >> MINOR_VER_BITS, version_raw & ((1 << MINOR_VER_BITS) - 1))
     };
     
-    // Encode LKP info
-    let lkpinfo = ((chid as u64) << 46)
-        | ((count as u64) << 32)
-        | (2u64 << 18)
-        | (144u64 << 10)
-        | ((version as u64) << 3);
+    let description = describe_license(chid, major_ver, minor_ver).map(str::to_string);
     
-    let lkpdata = bigint_to_bytes_le(&BigUint::from(lkpinfo), 7);
-    
-    if lkpdata.len() != 7 {
-        anyhow::bail!("LKP Info did not convert to 7 bytes");
+    Ok(LkpInfo {
+        chid,
+        count,
+        major_ver,
+        minor_ver,
+        version_raw,
+        description,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_lkpinfo_bounds() {
+        let max = encode_lkpinfo(9999, 1023, 15, 7).unwrap();
+        assert_eq!(max >> 46, 1023);
+        assert_eq!((max >> 32) & 0x3FFF, 9999);
+        assert_eq!((max >> 3) & 0x7F, 127);
+        assert!(max < 1 << 56);
+
+        assert!(encode_lkpinfo(9999, 1024, 10, 2).is_err());
+        assert!(encode_lkpinfo(1 << 14, 29, 10, 2).is_err());
+        assert!(encode_lkpinfo(1, 29, 10, 8).is_err());
+        assert!(encode_lkpinfo(1, 29, 16, 0).is_err());
+        // Would shift to version 0 without the range check
+        assert!(encode_lkpinfo(1, 29, 1 << 29, 0).is_err());
+        assert!(LicenseInfo::parse_raw("029_536870912_0")
+            .and_then(|info| generate_lkp(
+                "00490-92005-99454-AT527",
+                1,
+                info.chid,
+                info.major_ver,
+                info.minor_ver
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn test_encode_lkpinfo_version_branch() {
+        // Windows 2000 (5.0) and older collapse to version 1; 5.1 and later pack major.minor
+        let lkpdata = |count, chid, major_ver, minor_ver| {
+            let lkpinfo = encode_lkpinfo(count, chid, major_ver, minor_ver).unwrap();
+            bigint_to_bytes_le(&BigUint::from(lkpinfo), 7).unwrap()
+        };
+
+        // 001_5_0: version 1
+        assert_eq!(encode_lkpinfo(100, 1, 5, 0).unwrap(), 0x4064_000A_4008);
+        assert_eq!(lkpdata(100, 1, 5, 0), [0x08, 0x40, 0x0A, 0x00, 0x64, 0x40, 0x00]);
+        // 002_5_0 differs only in the CHID
+        assert_eq!(lkpdata(100, 2, 5, 0), [0x08, 0x40, 0x0A, 0x00, 0x64, 0x80, 0x00]);
+        // 029_10_2: version (10 << 3) | 2
+        assert_eq!(encode_lkpinfo(100, 29, 10, 2).unwrap(), 0x7_4064_000A_4290);
+        assert_eq!(lkpdata(100, 29, 10, 2), [0x90, 0x42, 0x0A, 0x00, 0x64, 0x40, 0x07]);
+
+        let version = |major_ver, minor_ver| (encode_lkpinfo(1, 1, major_ver, minor_ver).unwrap() >> 3) & 0x7F;
+        assert_eq!(version(4, 0), 1);
+        assert_eq!(version(5, 0), 1);
+        assert_eq!(version(5, 1), (5 << 3) | 1);
+        assert_eq!(version(6, 0), 6 << 3);
+    }
+
+    #[test]
+    fn test_inspect_lkp() {
+        let pid = "00490-92005-99454-AT527";
+        for (count, chid, major_ver, minor_ver) in [(250, 29, 10, 2), (1, 1, 5, 0)] {
+            let lkp = generate_lkp(pid, count, chid, major_ver, minor_ver).unwrap();
+            let info = inspect_lkp(pid, &lkp).unwrap();
+            assert_eq!(
+                (info.count, info.chid, info.major_ver, info.minor_ver),
+                (count, chid, major_ver, minor_ver)
+            );
+            assert!(info.description.is_some());
+        }
+    }
+
+    #[test]
+    fn test_generate_lkp_stream() {
+        let pid = "00490-92005-99454-AT527";
+        let requests: Vec<(u32, LicenseInfo)> = [(0, "029_10_2"), (250, "029_10_2"), (1, "001_5_0")]
+            .into_iter()
+            .map(|(count, code)| (count, LicenseInfo::parse(code).unwrap()))
+            .collect();
+
+        let mut stream = generate_lkp_stream(pid, &requests);
+        assert!(stream.next().unwrap().is_err());
+        let info = inspect_lkp(pid, &stream.next().unwrap().unwrap()).unwrap();
+        assert_eq!((info.count, info.chid), (250, 29));
+        assert_eq!(stream.count(), 1);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_generate_lkp_batch_keeps_order() {
+        let pid = "00490-92005-99454-AT527";
+        let requests: Vec<(u32, LicenseInfo)> = [(1, "001_5_0"), (250, "029_10_2"), (0, "029_10_2"), (9999, "003_5_2")]
+            .into_iter()
+            .map(|(count, code)| (count, LicenseInfo::parse(code).unwrap()))
+            .collect();
+
+        let results = generate_lkp_batch(pid, &requests);
+        assert_eq!(results.len(), requests.len());
+        for ((count, license), result) in requests.iter().zip(&results) {
+            if *count == 0 {
+                assert!(result.is_err());
+                continue;
+            }
+            let info = inspect_lkp(pid, result.as_ref().unwrap()).unwrap();
+            assert_eq!((info.count, info.chid), (*count, license.chid));
+        }
     }
-    
-    generate_tskey(
-        pid,
-        &lkpdata,
-        LKPCurve::gx(),
-        LKPCurve::gy(),
-        BigUint::from(LKPCurve::A),
-        LKPCurve::p(),
-        LKPCurve::n(),
-        LKPCurve::priv_key(),
-        1000,
-    )
 }
@@ -1,21 +1,69 @@
 //! Key generation module
 
 pub mod lkp;
+mod rfc6979;
 pub mod spk;
 pub mod validation;
 
-pub use lkp::generate_lkp;
-pub use spk::generate_spk;
-pub use validation::validate_tskey;
+pub use lkp::{generate_lkp, generate_lkp_rfc6979, generate_lkp_stream, generate_lkp_with_curve, inspect_lkp, LkpInfo};
+#[cfg(feature = "parallel")]
+pub use lkp::generate_lkp_batch;
+pub use spk::{
+    generate_spk, generate_spk_for_spkid, generate_spk_many, generate_spk_many_with_curve, generate_spk_rfc6979,
+    generate_spk_with_curve, inspect_spk,
+};
+pub use validation::{
+    diagnose_tskey, signature_components, validate_lkp, validate_tskey, validate_tskey_detailed, KeyDiagnosis,
+    Validator,
+};
+use validation::check_tskey;
 
-use crate::crypto::{bigint_to_bytes_le, bytes_to_bigint_le, encode_pkey, rc4_crypt, EllipticCurvePoint};
+use crate::crypto::{bigint_to_bytes_le, bytes_to_bigint_le, derive_rc4_key, encode_pkey, rc4_crypt, EllipticCurvePoint};
+use crate::types::{CurveParams, KeyKind};
 use num_bigint::BigUint;
-use num_traits::Zero;
 use rand::Rng;
 use sha1::{Digest, Sha1};
+use std::time::{Duration, Instant};
 
-/// Extract SPK ID from Product ID
-pub fn get_spkid(pid: &str) -> anyhow::Result<u64> {
+/// Normalize a pasted Product ID: drop all whitespace and uppercase letters
+pub fn normalize_pid(pid: &str) -> String {
+    pid.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Check that a Product ID has the `XXXXX-XXXXX-XXXXX-XXXXX` layout and a parsable SPKID
+pub fn validate_pid(pid: &str) -> anyhow::Result<()> {
+    let groups: Vec<&str> = pid.split('-').collect();
+    if groups.len() != 4 || groups.iter().any(|g| g.len() != 5) {
+        anyhow::bail!("PID must be four groups of 5 characters (e.g., 00490-92005-99454-AT527)");
+    }
+    
+    if let Some(ch) = pid.chars().find(|c| *c != '-' && !c.is_ascii_alphanumeric()) {
+        anyhow::bail!("Invalid character in PID: {}", ch);
+    }
+    
+    get_spkid(pid)?;
+    Ok(())
+}
+
+/// SPK ID together with the raw PID substrings it was derived from
+#[derive(Debug, Clone)]
+pub struct SpkidDerivation {
+    /// `pid[10..16]`
+    pub part1: String,
+    /// `pid[18..23]`
+    pub part2: String,
+    pub spkid: u64,
+}
+
+/// Extract SPK ID from Product ID, keeping the substrings used
+pub fn derive_spkid(pid: &str) -> anyhow::Result<SpkidDerivation> {
+    // The SPKID is sliced out by byte offset, which needs one byte per character
+    if let Some(ch) = pid.chars().find(|c| !c.is_ascii()) {
+        anyhow::bail!("Invalid character in PID: {}", ch);
+    }
     if pid.len() < 23 {
         anyhow::bail!("Invalid PID length");
     }
@@ -25,43 +73,218 @@ pub fn get_spkid(pid: &str) -> anyhow::Result<u64> {
     let combined = format!("{}{}", spkid_part1, spkid_part2);
     let spkid_str = combined.split('-').next().unwrap_or("");
     
-    spkid_str.parse::<u64>()
-        .map_err(|e| anyhow::anyhow!("Failed to parse SPKID: {}", e))
+    let spkid = spkid_str.parse::<u64>()
+        .map_err(|e| anyhow::anyhow!("Failed to parse SPKID: {}", e))?;
+    
+    Ok(SpkidDerivation {
+        part1: spkid_part1.to_string(),
+        part2: spkid_part2.to_string(),
+        spkid,
+    })
+}
+
+/// Extract SPK ID from Product ID
+pub fn get_spkid(pid: &str) -> anyhow::Result<u64> {
+    Ok(derive_spkid(pid)?.spkid)
 }
 
+/// Default number of signing attempts made by `generate_spk` and `generate_lkp`
+///
+/// Each attempt yields a valid key with a probability of roughly 1 in 20, so `N` attempts
+/// all fail with probability about `(19/20)^N`: ~36% for 20, ~0.6% for 100 and
+/// effectively never (~e^-51) for 1000. Failed attempts only cost time.
+pub const DEFAULT_MAX_ATTEMPTS: usize = 1000;
+
 /// Generate Terminal Services key (generic function for both SPK and LKP)
-pub fn generate_tskey(
+///
+/// Nonces are drawn from `rng`; pass a seeded RNG for reproducible output.
+pub fn generate_tskey<R: Rng + ?Sized>(
+    pid: &str,
+    keydata_inner: &[u8],
+    curve: &CurveParams,
+    kind: KeyKind,
+    max_attempts: usize,
+    rng: &mut R,
+) -> anyhow::Result<String> {
+    generate_tskey_with_timeout(pid, keydata_inner, curve, kind, max_attempts, None, rng)
+}
+
+/// `generate_tskey` that also gives up with [`GenerationTimedOut`] once `timeout` has passed
+///
+/// The clock is checked before each attempt, so the overrun is at most one attempt.
+pub fn generate_tskey_with_timeout<R: Rng + ?Sized>(
+    pid: &str,
+    keydata_inner: &[u8],
+    curve: &CurveParams,
+    kind: KeyKind,
+    max_attempts: usize,
+    timeout: Option<Duration>,
+    rng: &mut R,
+) -> anyhow::Result<String> {
+    generate_tskey_with_progress(pid, keydata_inner, curve, kind, max_attempts, timeout, rng, |_| {})
+}
+
+/// `generate_tskey_with_timeout` that calls `on_attempt` with the attempt number
+/// (starting at 1) before each signing attempt, e.g. to drive a progress bar
+#[allow(clippy::too_many_arguments)]
+pub fn generate_tskey_with_progress<R: Rng + ?Sized>(
+    pid: &str,
+    keydata_inner: &[u8],
+    curve: &CurveParams,
+    kind: KeyKind,
+    max_attempts: usize,
+    timeout: Option<Duration>,
+    rng: &mut R,
+    mut on_attempt: impl FnMut(usize),
+) -> anyhow::Result<String> {
+    // Exactly one nonce is drawn per attempt, so counting draws counts attempts
+    let mut attempt = 0;
+    let mut next_nonce = || {
+        attempt += 1;
+        on_attempt(attempt);
+        random_nonce(rng, &curve.n)
+    };
+    sign_tskey(pid, keydata_inner, curve, kind, true, max_attempts, timeout, &mut next_nonce)
+        .map(|trace| trace.key)
+}
+
+/// `generate_tskey_with_timeout` with nonces derived from the private key and
+/// `keydata_inner` (RFC 6979 with HMAC-SHA1) instead of drawn at random
+///
+/// The same input always yields the same key.
+pub fn generate_tskey_rfc6979(
     pid: &str,
     keydata_inner: &[u8],
-    gx: BigUint,
-    gy: BigUint,
-    a: BigUint,
-    p: BigUint,
-    n: BigUint,
-    priv_key: BigUint,
+    curve: &CurveParams,
+    kind: KeyKind,
     max_attempts: usize,
+    timeout: Option<Duration>,
 ) -> anyhow::Result<String> {
-    // Determine if this is SPK based on curve parameters
-    let is_spk = n == crate::types::SPKCurve::n();
-    // Generate RC4 key from PID
-    let pid_utf16le = encode_utf16_le(pid);
-    let md5_digest = md5::compute(&pid_utf16le);
-    let mut rk = md5_digest[..5].to_vec();
-    rk.extend_from_slice(&[0u8; 11]);
+    check_order(&curve.n)?;
+    let mut nonces = rfc6979::Rfc6979::new(&curve.priv_key, &curve.n, keydata_inner);
+    let mut next_nonce = || nonces.next_nonce();
+    sign_tskey(pid, keydata_inner, curve, kind, true, max_attempts, timeout, &mut next_nonce)
+        .map(|trace| trace.key)
+}
+
+/// `generate_tskey` that also returns every intermediate value of the successful attempt
+pub fn generate_tskey_traced<R: Rng + ?Sized>(
+    pid: &str,
+    keydata_inner: &[u8],
+    curve: &CurveParams,
+    kind: KeyKind,
+    max_attempts: usize,
+    rng: &mut R,
+) -> anyhow::Result<SigningTrace> {
+    sign_tskey(pid, keydata_inner, curve, kind, true, max_attempts, None, &mut || random_nonce(rng, &curve.n))
+}
+
+/// Intermediate values of one successful signing attempt, in computation order
+#[derive(Debug, Clone, PartialEq)]
+pub struct SigningTrace {
+    /// RC4 key: the first 5 bytes of MD5(UTF-16LE PID), zero-padded to 16 bytes
+    pub rc4_key: Vec<u8>,
+    /// Attempts made, including the successful one
+    pub attempts: usize,
+    pub nonce: BigUint,
+    /// `R = nonce * G`
+    pub rx: BigUint,
+    pub ry: BigUint,
+    /// Key data followed by `R.x` and `R.y` as 48-byte little-endian integers
+    pub sha1_input: Vec<u8>,
+    pub sha1_digest: Vec<u8>,
+    /// 35-bit hash taken from the digest
+    pub h: BigUint,
+    /// `s = (nonce - priv_key * h) mod n`, which fits the 69-bit mask
+    pub s: BigUint,
+    /// `s << 35 | h`
+    pub sigdata: BigUint,
+    /// Key data followed by the 14-byte signature, before encryption
+    pub pkdata: Vec<u8>,
+    /// RC4-encrypted `pkdata`
+    pub encrypted: Vec<u8>,
+    pub key: String,
+}
+
+/// Reject curve orders that leave no nonce in `[1, n)` to sign with
+fn check_order(n: &BigUint) -> anyhow::Result<()> {
+    if *n <= BigUint::from(1u32) {
+        anyhow::bail!("Curve order n must be greater than 1 (got {})", n);
+    }
+    Ok(())
+}
+
+/// Uniformly random nonce in `[1, n)`
+///
+/// Candidates of `n.bits()` random bits are drawn until one falls in range, so
+/// fewer than two draws are needed on average. (The reference implementation
+/// reduces a random `u64` modulo the low 64 bits of `n`, which never reaches
+/// most of the range on curves whose order is wider than 64 bits.)
+pub(crate) fn random_nonce<R: Rng + ?Sized>(rng: &mut R, n: &BigUint) -> BigUint {
+    let bits = n.bits();
+    let mut bytes = vec![0u8; bits.div_ceil(8) as usize];
+    loop {
+        rng.fill_bytes(&mut bytes);
+        // Big-endian, so the excess bits are the top ones of the first byte
+        bytes[0] &= 0xFF >> (bytes.len() as u64 * 8 - bits);
+        let nonce = BigUint::from_bytes_be(&bytes);
+        if nonce > BigUint::from(0u32) && nonce < *n {
+            return nonce;
+        }
+    }
+}
+
+/// Signing loop behind the `generate_tskey` variants, taking one nonce per attempt
+/// from `next_nonce`
+///
+/// With `check_spkid`, SPK candidates must also carry the SPKID derived from `pid`;
+/// without it only the signature is checked, for key data not derived from `pid`.
+#[allow(clippy::too_many_arguments)]
+fn sign_tskey(
+    pid: &str,
+    keydata_inner: &[u8],
+    curve: &CurveParams,
+    kind: KeyKind,
+    check_spkid: bool,
+    max_attempts: usize,
+    timeout: Option<Duration>,
+    next_nonce: &mut dyn FnMut() -> BigUint,
+) -> anyhow::Result<SigningTrace> {
+    let started = Instant::now();
+    let n = &curve.n;
+    let priv_key = &curve.priv_key;
+    let rk = derive_rc4_key(pid);
     
-    let g = EllipticCurvePoint::new(gx.clone(), gy.clone(), a.clone(), p.clone());
+    if max_attempts == 0 {
+        anyhow::bail!("max_attempts must be at least 1");
+    }
+    check_order(n)?;
+    
+    let g = curve.generator();
+    let mut stats = AttemptStats::default();
+    // First validation error, and whether every later one had the same message
+    let mut validation_error: Option<anyhow::Error> = None;
+    let mut validation_errors_agree = true;
     
-    for _ in 0..max_attempts {
-        // Generate random nonce
-        let mut rng = rand::thread_rng();
-        let c_nonce = BigUint::from(rng.gen::<u64>() % n.to_u64_digits()[0]) + BigUint::from(1u32);
+    for attempt in 1..=max_attempts {
+        if let Some(timeout) = timeout.filter(|t| started.elapsed() >= *t) {
+            tracing::warn!(kind = kind.name(), attempts = attempt - 1, ?timeout, "timed out generating key");
+            return Err(GenerationTimedOut { kind, attempts: attempt - 1, timeout }.into());
+        }
+        
+        let c_nonce = next_nonce();
+        tracing::debug!(kind = kind.name(), attempt, nonce_bits = c_nonce.bits(), "trying nonce");
         
         // Calculate R = c_nonce * G
-        let r = g.mul(&c_nonce);
+        let Some(r) = nonce_point(&g, &c_nonce) else {
+            tracing::debug!(kind = kind.name(), attempt, "rejected candidate: R is the point at infinity");
+            stats.infinity_rejects += 1;
+            continue;
+        };
         
         // Calculate hash
-        let rx_bytes = bigint_to_bytes_le(&r.x, 48);
-        let ry_bytes = bigint_to_bytes_le(&r.y, 48);
+        let rx_bytes = bigint_to_bytes_le(&r.x, 48)?;
+        let ry_bytes = bigint_to_bytes_le(&r.y, 48)?;
         
         let mut sha1_input = keydata_inner.to_vec();
         sha1_input.extend_from_slice(&rx_bytes);
@@ -75,10 +298,10 @@ pub fn generate_tskey(
         let h = (&part2 << 32) | &part1;
         
         // Calculate signature: s = (c_nonce - priv_key * h) mod n
-        let s = if &c_nonce >= &(&priv_key * &h % &n) {
-            (&c_nonce - (&priv_key * &h % &n)) % &n
+        let s = if c_nonce >= (priv_key * &h % n) {
+            (&c_nonce - (priv_key * &h % n)) % n
         } else {
-            (&n + &c_nonce - (&priv_key * &h % &n)) % &n
+            (n + &c_nonce - (priv_key * &h % n)) % n
         };
         
         // Mask values (69 bits for s, 35 bits for h)
@@ -90,61 +313,393 @@ pub fn generate_tskey(
         
         // Check if s fits in the mask
         if s_masked != s || s_masked >= s_mask {
+            tracing::warn!(kind = kind.name(), attempt, "rejected candidate: s does not fit the 69-bit mask");
+            stats.mask_rejects += 1;
             continue;
         }
         
         // Encode signature
         let sigdata = (&s_masked << 35) | &h_masked;
-        let sigdata_bytes = bigint_to_bytes_le(&sigdata, 14);
+        let sigdata_bytes = bigint_to_bytes_le(&sigdata, 14)?;
         
         let mut pkdata = keydata_inner.to_vec();
         pkdata.extend_from_slice(&sigdata_bytes);
         
         if pkdata.len() != 21 {
+            tracing::warn!(kind = kind.name(), attempt, len = pkdata.len(), "rejected candidate: pkdata is not 21 bytes");
+            stats.length_rejects += 1;
             continue;
         }
         
         // Encrypt
-        let pke = rc4_crypt(&rk, &pkdata);
+        let pke = rc4_crypt(&rk, &pkdata)?;
         let pk = bytes_to_bigint_le(&pke[..20]);
         let pkstr = encode_pkey(&pk);
         
         // Validate the generated key
-        match validate_tskey(
-            pid,
-            &pkstr,
-            gx.clone(),
-            gy.clone(),
-            // For validation, we need Kx and Ky (public key)
-            if is_spk {
-                crate::types::SPKCurve::kx()
-            } else {
-                crate::types::LKPCurve::kx()
-            },
-            if is_spk {
-                crate::types::SPKCurve::ky()
-            } else {
-                crate::types::LKPCurve::ky()
-            },
-            a.clone(),
-            p.clone(),
-            is_spk,
-        ) {
-            Ok(true) => return Ok(pkstr),
-            _ => continue,
+        match check_tskey(pid, &pkstr, curve, kind == KeyKind::Spk && check_spkid) {
+            Ok(KeyDiagnosis::Valid) => {
+                tracing::info!(kind = kind.name(), attempts = attempt, curve_n = %n, "generated key");
+                return Ok(SigningTrace {
+                    rc4_key: rk.to_vec(),
+                    attempts: attempt,
+                    nonce: c_nonce,
+                    rx: r.x,
+                    ry: r.y,
+                    sha1_input,
+                    sha1_digest: md.to_vec(),
+                    h,
+                    s,
+                    sigdata,
+                    pkdata,
+                    encrypted: pke,
+                    key: pkstr,
+                });
+            }
+            Ok(_) => {
+                tracing::warn!(kind = kind.name(), attempt, "rejected candidate: failed validation");
+                stats.validation_rejects += 1;
+            }
+            Err(e) => {
+                tracing::warn!(kind = kind.name(), attempt, error = %e, "rejected candidate: validation error");
+                stats.validation_errors += 1;
+                match &validation_error {
+                    Some(first) => validation_errors_agree &= first.to_string() == e.to_string(),
+                    None => validation_error = Some(e),
+                }
+            }
+        }
+    }
+    
+    // Our own candidates never make the validator error, so an error that repeats
+    // points at a systemic problem (e.g. curve parameters or PID), not at bad luck
+    if validation_errors_agree {
+        if let Some(cause) = validation_error {
+            return Err(GenerationValidationFailed { kind, stats, cause }.into());
         }
     }
     
-    anyhow::bail!("Failed to generate valid key after {} attempts", max_attempts)
+    tracing::warn!(
+        kind = kind.name(),
+        attempts = max_attempts,
+        infinity_rejects = stats.infinity_rejects,
+        mask_rejects = stats.mask_rejects,
+        length_rejects = stats.length_rejects,
+        validation_rejects = stats.validation_rejects,
+        validation_errors = stats.validation_errors,
+        "gave up generating key"
+    );
+    Err(GenerationExhausted { kind, stats }.into())
+}
+
+/// `R = c_nonce * G`, or `None` if that is the point at infinity (no usable hash input)
+fn nonce_point(g: &EllipticCurvePoint, c_nonce: &BigUint) -> Option<EllipticCurvePoint> {
+    let r = g.mul(c_nonce);
+    (!r.infinity).then_some(r)
 }
 
-/// Encode string to UTF-16 LE bytes
-fn encode_utf16_le(s: &str) -> Vec<u8> {
-    let utf16: Vec<u16> = s.encode_utf16().collect();
-    let mut bytes = Vec::with_capacity(utf16.len() * 2);
-    for word in utf16 {
-        bytes.push((word & 0xFF) as u8);
-        bytes.push((word >> 8) as u8);
+/// Why each rejected signing attempt was thrown away
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AttemptStats {
+    /// `R = nonce * G` was the point at infinity
+    pub infinity_rejects: usize,
+    /// `s` did not fit the 69-bit signature field
+    pub mask_rejects: usize,
+    /// Packed key data was not 21 bytes
+    pub length_rejects: usize,
+    /// Encoded key did not validate against the curve
+    pub validation_rejects: usize,
+    /// Validating the encoded key returned an error
+    pub validation_errors: usize,
+}
+
+impl AttemptStats {
+    pub fn total(&self) -> usize {
+        self.infinity_rejects
+            + self.mask_rejects
+            + self.length_rejects
+            + self.validation_rejects
+            + self.validation_errors
+    }
+}
+
+/// Error returned by [`generate_tskey`] when every attempt was rejected
+///
+/// Recover it from the `anyhow::Error` with `downcast_ref` to inspect the breakdown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenerationExhausted {
+    pub kind: KeyKind,
+    pub stats: AttemptStats,
+}
+
+impl std::fmt::Display for GenerationExhausted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Failed to generate valid {} after {} attempts ({} at infinity, {} outside the signature mask, {} with a bad length, {} failed validation, {} validation errors)",
+            self.kind.name(),
+            self.stats.total(),
+            self.stats.infinity_rejects,
+            self.stats.mask_rejects,
+            self.stats.length_rejects,
+            self.stats.validation_rejects,
+            self.stats.validation_errors
+        )
+    }
+}
+
+impl std::error::Error for GenerationExhausted {}
+
+/// Error returned by [`generate_tskey`] instead of [`GenerationExhausted`] when
+/// `validate_tskey` errored for some candidates, always with the same message
+#[derive(Debug)]
+pub struct GenerationValidationFailed {
+    pub kind: KeyKind,
+    pub stats: AttemptStats,
+    /// First validation error
+    pub cause: anyhow::Error,
+}
+
+impl std::fmt::Display for GenerationValidationFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Failed to generate valid {}: validation errored for {} of {} attempts: {}",
+            self.kind.name(),
+            self.stats.validation_errors,
+            self.stats.total(),
+            self.cause
+        )
+    }
+}
+
+impl std::error::Error for GenerationValidationFailed {}
+
+/// Error returned by [`generate_tskey_with_timeout`] when the time limit ran out first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenerationTimedOut {
+    pub kind: KeyKind,
+    /// Attempts completed before giving up
+    pub attempts: usize,
+    pub timeout: Duration,
+}
+
+impl std::fmt::Display for GenerationTimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Timed out generating {} after {:.1}s ({} attempts)",
+            self.kind.name(),
+            self.timeout.as_secs_f64(),
+            self.attempts
+        )
+    }
+}
+
+impl std::error::Error for GenerationTimedOut {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_normalize_and_validate_pid() {
+        let pid = normalize_pid("  00490-92005 - 99454-at527\n");
+        assert_eq!(pid, "00490-92005-99454-AT527");
+        assert!(validate_pid(&pid).is_ok());
+        assert!(validate_pid("00490-92005-99454").is_err());
+        assert!(validate_pid("00490-92005-99454-AT52!").is_err());
+    }
+    
+    #[test]
+    fn test_generate_validate_round_trip() {
+        use crate::types::{LKPCurve, SPKCurve};
+        
+        let pid = "00490-92005-99454-AT527";
+        let (spk_curve, lkp_curve) = (SPKCurve::params(), LKPCurve::params());
+        assert!(spk_curve.generator().is_on_curve() && lkp_curve.generator().is_on_curve());
+        
+        let spk = generate_spk(pid).unwrap();
+        assert!(validate_tskey(pid, &spk, &spk_curve, KeyKind::Spk).unwrap());
+        
+        let lkp = generate_lkp(pid, 10, 29, 10, 2).unwrap();
+        assert!(validate_tskey(pid, &lkp, &lkp_curve, KeyKind::Lkp).unwrap());
+        assert!(!validate_tskey(pid, &lkp, &spk_curve, KeyKind::Spk).unwrap());
+    }
+    
+    #[test]
+    fn test_nonce_point_rejects_infinity() {
+        use crate::types::SPKCurve;
+        
+        let curve = SPKCurve::params();
+        let g = curve.generator();
+        assert!(nonce_point(&g, &curve.n).is_none());
+        assert!(nonce_point(&g, &BigUint::from(1u32)).is_some_and(|r| r.x == g.x && r.y == g.y));
+    }
+    
+    #[test]
+    fn test_odd_inputs_error_instead_of_panicking() {
+        use crate::types::SPKCurve;
+        
+        // Multi-byte characters around the SPKID byte offsets
+        for pid in ["00490-9200😀-99454-AT527", "00490-92005-99454-AT5😀"] {
+            assert!(get_spkid(pid).unwrap_err().to_string().contains("Invalid character"));
+            assert!(validate_pid(pid).is_err());
+        }
+        
+        let pid = "00490-92005-99454-AT527";
+        let spkdata = bigint_to_bytes_le(&BigUint::from(get_spkid(pid).unwrap()), 7).unwrap();
+        for n in [0u32, 1] {
+            let curve = CurveParams { n: BigUint::from(n), ..SPKCurve::params() };
+            let err = generate_tskey(pid, &spkdata, &curve, KeyKind::Spk, 10, &mut rand::thread_rng()).unwrap_err();
+            assert!(err.to_string().contains("Curve order"));
+            assert!(generate_tskey_rfc6979(pid, &spkdata, &curve, KeyKind::Spk, 10, None).is_err());
+        }
+    }
+    
+    #[test]
+    fn test_random_nonce_covers_full_range() {
+        use crate::types::SPKCurve;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        
+        // The SPK curve order is about 2^69, so most nonces need more than 64 bits
+        let n = SPKCurve::params().n;
+        let mut rng = StdRng::seed_from_u64(0);
+        let nonces: Vec<BigUint> = (0..64).map(|_| random_nonce(&mut rng, &n)).collect();
+        assert!(nonces.iter().all(|k| *k > BigUint::from(0u32) && *k < n));
+        assert!(nonces.iter().any(|k| *k > BigUint::from(u64::MAX)));
+        
+        // A small order still gets every value in [1, n)
+        let n = BigUint::from(3u32);
+        let mut seen: Vec<BigUint> = (0..50).map(|_| random_nonce(&mut rng, &n)).collect();
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen, [BigUint::from(1u32), BigUint::from(2u32)]);
+    }
+    
+    #[test]
+    fn test_traced_matches_untraced() {
+        use crate::types::SPKCurve;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        
+        let pid = "00490-92005-99454-AT527";
+        let curve = SPKCurve::params();
+        let spkdata = bigint_to_bytes_le(&BigUint::from(get_spkid(pid).unwrap()), 7).unwrap();
+        let key = generate_tskey(pid, &spkdata, &curve, KeyKind::Spk, 100, &mut StdRng::seed_from_u64(3)).unwrap();
+        let trace = generate_tskey_traced(pid, &spkdata, &curve, KeyKind::Spk, 100, &mut StdRng::seed_from_u64(3)).unwrap();
+        
+        assert_eq!(trace.key, key);
+        assert_eq!(trace.rc4_key.len(), 16);
+        assert_eq!(trace.sigdata, (&trace.s << 35) | &trace.h);
+        assert_eq!(&trace.pkdata[..7], &spkdata[..]);
+        let r = curve.generator().mul(&trace.nonce);
+        assert_eq!((r.x, r.y), (trace.rx, trace.ry));
+    }
+    
+    #[test]
+    fn test_progress_reports_every_attempt() {
+        use crate::types::SPKCurve;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        
+        let pid = "00490-92005-99454-AT527";
+        let curve = SPKCurve::params();
+        let spkdata = bigint_to_bytes_le(&BigUint::from(get_spkid(pid).unwrap()), 7).unwrap();
+        let mut seen = Vec::new();
+        let key = generate_tskey_with_progress(
+            pid,
+            &spkdata,
+            &curve,
+            KeyKind::Spk,
+            100,
+            None,
+            &mut StdRng::seed_from_u64(3),
+            |attempt| seen.push(attempt),
+        )
+        .unwrap();
+        let trace = generate_tskey_traced(pid, &spkdata, &curve, KeyKind::Spk, 100, &mut StdRng::seed_from_u64(3)).unwrap();
+        
+        assert_eq!(key, trace.key);
+        assert_eq!(seen, (1..=trace.attempts).collect::<Vec<_>>());
+    }
+    
+    #[test]
+    fn test_rfc6979_is_deterministic() {
+        use crate::types::SPKCurve;
+        
+        let pid = "00490-92005-99454-AT527";
+        let curve = SPKCurve::params();
+        let spkdata = bigint_to_bytes_le(&BigUint::from(get_spkid(pid).unwrap()), 7).unwrap();
+        let generate = |pid: &str| generate_tskey_rfc6979(pid, &spkdata, &curve, KeyKind::Spk, 100, None).unwrap();
+        
+        let key = generate(pid);
+        assert_eq!(key, generate(pid));
+        assert!(validate_tskey(pid, &key, &curve, KeyKind::Spk).unwrap());
+        
+        // Same SPKID and so the same signature, but encrypted under another PID's RC4 key
+        let other_pid = "11111-92005-99454-AT527";
+        assert_ne!(generate(other_pid), key);
+    }
+    
+    #[test]
+    fn test_timeout() {
+        use crate::types::SPKCurve;
+        
+        let pid = "00490-92005-99454-AT527";
+        let spkdata = bigint_to_bytes_le(&BigUint::from(get_spkid(pid).unwrap()), 7).unwrap();
+        let err = generate_tskey_with_timeout(
+            pid,
+            &spkdata,
+            &SPKCurve::params(),
+            KeyKind::Spk,
+            DEFAULT_MAX_ATTEMPTS,
+            Some(Duration::ZERO),
+            &mut rand::thread_rng(),
+        )
+        .unwrap_err();
+        let timed_out = err.downcast_ref::<GenerationTimedOut>().unwrap();
+        assert_eq!((timed_out.kind, timed_out.attempts), (KeyKind::Spk, 0));
+    }
+    
+    #[test]
+    fn test_exhaustion_reports_stats() {
+        use crate::types::SPKCurve;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        
+        // A private key that does not match the public key never validates
+        let mut curve = SPKCurve::params();
+        curve.priv_key += 1u32;
+        let pid = "00490-92005-99454-AT527";
+        let spkdata = bigint_to_bytes_le(&BigUint::from(get_spkid(pid).unwrap()), 7).unwrap();
+        
+        let err = generate_tskey(pid, &spkdata, &curve, KeyKind::Spk, 20, &mut StdRng::seed_from_u64(0)).unwrap_err();
+        let exhausted = err.downcast_ref::<GenerationExhausted>().unwrap();
+        assert_eq!(exhausted.kind, KeyKind::Spk);
+        assert_eq!(exhausted.stats.total(), 20);
+        assert!(exhausted.stats.validation_rejects > 0);
+        assert_eq!(exhausted.stats.validation_errors, 0);
+    }
+    
+    #[test]
+    fn test_systemic_validation_error_is_surfaced() {
+        use crate::types::SPKCurve;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        
+        // A PID without a numeric SPKID: every correctly signed candidate errors in
+        // the SPKID comparison
+        let spkdata = bigint_to_bytes_le(&BigUint::from(5u32), 7).unwrap();
+        let pid = "ABCDE-FGHIJ-KLMNO-PQRST";
+        
+        let err = generate_tskey(pid, &spkdata, &SPKCurve::params(), KeyKind::Spk, 200, &mut StdRng::seed_from_u64(0))
+            .unwrap_err();
+        let failed = err.downcast_ref::<GenerationValidationFailed>().unwrap();
+        assert_eq!(failed.kind, KeyKind::Spk);
+        assert_eq!(failed.stats.total(), 200);
+        assert!(failed.stats.validation_errors > 0);
+        assert!(failed.cause.to_string().contains("SPKID"));
     }
-    bytes
 }
@@ -0,0 +1,128 @@
+//! Deterministic signing nonces (RFC 6979 section 3.2, with HMAC-SHA1)
+//!
+//! The nonce is derived from the private key and the signed key data, so the same
+//! input always yields the same key and no randomness is needed.
+
+use num_bigint::BigUint;
+use sha1::{Digest, Sha1};
+
+/// SHA-1 block size in bytes
+const BLOCK_SIZE: usize = 64;
+
+/// HMAC-SHA1 of the concatenated `parts`
+fn hmac_sha1(key: &[u8], parts: &[&[u8]]) -> [u8; 20] {
+    // Keys here are at most one digest long, so they never need hashing first
+    debug_assert!(key.len() <= BLOCK_SIZE);
+    let mut block = [0u8; BLOCK_SIZE];
+    block[..key.len()].copy_from_slice(key);
+
+    let mut inner = Sha1::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    for part in parts {
+        inner.update(part);
+    }
+    let mut outer = Sha1::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+/// Leftmost `qlen` bits of `bytes` as an integer
+fn bits2int(bytes: &[u8], qlen: u64) -> BigUint {
+    let value = BigUint::from_bytes_be(bytes);
+    let blen = bytes.len() as u64 * 8;
+    if blen > qlen {
+        value >> (blen - qlen)
+    } else {
+        value
+    }
+}
+
+/// `value` as `len` big-endian bytes
+fn int2octets(value: &BigUint, len: usize) -> Vec<u8> {
+    let bytes = value.to_bytes_be();
+    let mut padded = vec![0u8; len.saturating_sub(bytes.len())];
+    padded.extend_from_slice(&bytes);
+    padded
+}
+
+/// Sequence of nonce candidates in `[1, n)` for one private key and message
+///
+/// Each call to [`Rfc6979::next_nonce`] continues where a rejected candidate left
+/// off (step h.3 of the RFC), so the signing loop can keep drawing until one fits.
+pub(crate) struct Rfc6979 {
+    n: BigUint,
+    qlen: u64,
+    k: [u8; 20],
+    v: [u8; 20],
+}
+
+impl Rfc6979 {
+    pub(crate) fn new(priv_key: &BigUint, n: &BigUint, message: &[u8]) -> Self {
+        let qlen = n.bits();
+        let rlen = qlen.div_ceil(8) as usize;
+        let x = int2octets(priv_key, rlen);
+        let h1 = int2octets(&(bits2int(&Sha1::digest(message), qlen) % n), rlen);
+
+        let mut v = [0x01; 20];
+        let mut k = hmac_sha1(&[0x00; 20], &[&v, &[0x00], &x, &h1]);
+        v = hmac_sha1(&k, &[&v]);
+        k = hmac_sha1(&k, &[&v, &[0x01], &x, &h1]);
+        v = hmac_sha1(&k, &[&v]);
+
+        Self {
+            n: n.clone(),
+            qlen,
+            k,
+            v,
+        }
+    }
+
+    pub(crate) fn next_nonce(&mut self) -> BigUint {
+        loop {
+            let mut t = Vec::new();
+            while (t.len() as u64) * 8 < self.qlen {
+                self.v = hmac_sha1(&self.k, &[&self.v]);
+                t.extend_from_slice(&self.v);
+            }
+            let nonce = bits2int(&t, self.qlen);
+
+            // Advance the state so the next call yields a fresh candidate
+            self.k = hmac_sha1(&self.k, &[&self.v, &[0x00]]);
+            self.v = hmac_sha1(&self.k, &[&self.v]);
+
+            if nonce > BigUint::from(0u32) && nonce < self.n {
+                return nonce;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha1_vector() {
+        // RFC 2202, test case 2
+        let mac = hmac_sha1(b"Jefe", &[b"what do ya ", b"want for nothing?"]);
+        let hex: String = mac.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79");
+    }
+
+    #[test]
+    fn test_nonces_are_deterministic_and_in_range() {
+        let n = BigUint::from(1_000_003u32);
+        let priv_key = BigUint::from(12_345u32);
+        let draw = |message: &[u8]| {
+            let mut nonces = Rfc6979::new(&priv_key, &n, message);
+            (0..5).map(|_| nonces.next_nonce()).collect::<Vec<_>>()
+        };
+
+        let first = draw(b"key data");
+        assert_eq!(first, draw(b"key data"));
+        assert_ne!(first, draw(b"other key data"));
+        assert!(first.iter().all(|k| *k > BigUint::from(0u32) && *k < n));
+        assert_ne!(first[0], first[1]);
+    }
+}
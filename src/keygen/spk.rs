@@ -1,28 +1,167 @@
 //! SPK (Service Provider Key) generation
 
-use crate::crypto::bigint_to_bytes_le;
-use crate::keygen::{generate_tskey, get_spkid};
-use crate::types::SPKCurve;
+use crate::crypto::{bigint_to_bytes_le, bytes_to_bigint_le};
+use crate::keygen::validation::decrypt_tskey;
+use crate::keygen::{
+    generate_tskey_rfc6979, generate_tskey_with_timeout, get_spkid, random_nonce, sign_tskey,
+    DEFAULT_MAX_ATTEMPTS,
+};
+use crate::types::{CurveParams, KeyKind, SPKCurve};
 use num_bigint::BigUint;
+use rand::Rng;
+use std::time::Duration;
+
+/// Largest SPKID an SPK can carry (41 bits)
+const MAX_SPKID: u64 = 0x1FF_FFFF_FFFF;
 
 /// Generate SPK (License Server ID)
 pub fn generate_spk(pid: &str) -> anyhow::Result<String> {
+    generate_spk_with_curve(pid, &SPKCurve::params(), DEFAULT_MAX_ATTEMPTS, None)
+}
+
+/// Generate SPK (License Server ID) signed on a custom curve, giving up after
+/// `max_attempts` or once `timeout` has passed
+pub fn generate_spk_with_curve(
+    pid: &str,
+    curve: &CurveParams,
+    max_attempts: usize,
+    timeout: Option<Duration>,
+) -> anyhow::Result<String> {
     let spkid_num = get_spkid(pid)?;
-    let spkdata = bigint_to_bytes_le(&BigUint::from(spkid_num), 7);
+    let spkdata = spkid_data(spkid_num)?;
     
-    if spkdata.len() != 7 {
-        anyhow::bail!("SPKID did not convert to 7 bytes");
+    generate_tskey_with_timeout(pid, &spkdata, curve, KeyKind::Spk, max_attempts, timeout, &mut rand::thread_rng())
+}
+
+/// Generate `count` distinct SPKs for the same PID
+///
+/// Every SPK is signed with a fresh random nonce, so each one validates for `pid`.
+pub fn generate_spk_many(pid: &str, count: usize) -> anyhow::Result<Vec<String>> {
+    generate_spk_many_with_curve(pid, count, &SPKCurve::params(), DEFAULT_MAX_ATTEMPTS, None)
+}
+
+/// `generate_spk_many` on a custom curve, with `max_attempts` and `timeout`
+/// applying to each SPK
+pub fn generate_spk_many_with_curve(
+    pid: &str,
+    count: usize,
+    curve: &CurveParams,
+    max_attempts: usize,
+    timeout: Option<Duration>,
+) -> anyhow::Result<Vec<String>> {
+    let mut spks: Vec<String> = Vec::with_capacity(count);
+    // Repeats are vanishingly rare on the real curves; the bound only stops tiny test curves looping
+    let mut duplicates = 0;
+    while spks.len() < count {
+        let spk = generate_spk_with_curve(pid, curve, max_attempts, timeout)?;
+        if spks.contains(&spk) {
+            duplicates += 1;
+            if duplicates > count {
+                anyhow::bail!("Only found {} distinct SPKs out of {} requested", spks.len(), count);
+            }
+            continue;
+        }
+        spks.push(spk);
+    }
+    Ok(spks)
+}
+
+/// `generate_spk_with_curve` with deterministic nonces: the same PID and curve
+/// always give the same SPK (see [`generate_tskey_rfc6979`])
+pub fn generate_spk_rfc6979(
+    pid: &str,
+    curve: &CurveParams,
+    max_attempts: usize,
+    timeout: Option<Duration>,
+) -> anyhow::Result<String> {
+    let spkdata = spkid_data(get_spkid(pid)?)?;
+    generate_tskey_rfc6979(pid, &spkdata, curve, KeyKind::Spk, max_attempts, timeout)
+}
+
+/// Generate an SPK carrying `spkid` without parsing it from a PID
+///
+/// Normally the SPKID is read from the PID (characters 11-16 and 19-23, see
+/// [`derive_spkid`](crate::keygen::derive_spkid)) and the PID also keys the RC4
+/// encryption of the SPK. Here only the encryption uses `pid_for_rc4`, so the SPK
+/// is bound to that PID string but may carry any SPKID; `validate_tskey` only
+/// accepts it for a PID whose own SPKID is `spkid`.
+pub fn generate_spk_for_spkid(spkid: u64, pid_for_rc4: &str) -> anyhow::Result<String> {
+    spk_for_spkid(spkid, pid_for_rc4, &mut rand::thread_rng())
+}
+
+fn spk_for_spkid<R: Rng + ?Sized>(spkid: u64, pid_for_rc4: &str, rng: &mut R) -> anyhow::Result<String> {
+    if spkid > MAX_SPKID {
+        anyhow::bail!("SPKID {} does not fit in 41 bits", spkid);
+    }
+    let spkdata = spkid_data(spkid)?;
+    let curve = SPKCurve::params();
+    let mut next_nonce = || random_nonce(rng, &curve.n);
+    sign_tskey(pid_for_rc4, &spkdata, &curve, KeyKind::Spk, false, DEFAULT_MAX_ATTEMPTS, None, &mut next_nonce)
+        .map(|trace| trace.key)
+}
+
+/// SPKID as the 7 bytes of SPK key data
+fn spkid_data(spkid: u64) -> anyhow::Result<Vec<u8>> {
+    bigint_to_bytes_le(&BigUint::from(spkid), 7)
+        .map_err(|_| anyhow::anyhow!("SPKID did not convert to 7 bytes"))
+}
+
+/// Decrypt an SPK and return the SPKID it carries
+///
+/// The signature is not checked; use `validate_tskey` for that.
+pub fn inspect_spk(pid: &str, spk: &str) -> anyhow::Result<u64> {
+    let keydata = decrypt_tskey(pid, spk)?;
+    let spkid = bytes_to_bigint_le(&keydata[..7]) & BigUint::from(MAX_SPKID);
+    Ok(spkid.to_u64_digits().first().copied().unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect_spk() {
+        let pid = "00490-12345-67890-AT123";
+        let spk = generate_spk(pid).unwrap();
+        assert_eq!(inspect_spk(pid, &spk).unwrap(), get_spkid(pid).unwrap());
+    }
+
+    #[test]
+    fn test_generate_spk_many() {
+        use crate::keygen::validate_tskey;
+
+        let pid = "00490-92005-99454-AT527";
+        let spks = generate_spk_many(pid, 3).unwrap();
+        assert_eq!(spks.len(), 3);
+        assert!(spks[0] != spks[1] && spks[1] != spks[2] && spks[0] != spks[2]);
+        assert!(spks.iter().all(|spk| validate_tskey(pid, spk, &SPKCurve::params(), KeyKind::Spk).unwrap()));
+        assert!(generate_spk_many(pid, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_spk_for_spkid_matches_pid_path() {
+        use crate::keygen::generate_tskey;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let pid = "00490-92005-99454-AT527";
+        let spkid = get_spkid(pid).unwrap();
+        let from_pid = generate_tskey(
+            pid,
+            &spkid_data(spkid).unwrap(),
+            &SPKCurve::params(),
+            KeyKind::Spk,
+            DEFAULT_MAX_ATTEMPTS,
+            &mut StdRng::seed_from_u64(7),
+        )
+        .unwrap();
+        let direct = spk_for_spkid(spkid, pid, &mut StdRng::seed_from_u64(7)).unwrap();
+        assert_eq!(direct, from_pid);
+
+        // A PID without a parsable SPKID still keys the encryption
+        let pid = "ABCDE-FGHIJ-KLMNO-PQRST";
+        let spk = generate_spk_for_spkid(1234, pid).unwrap();
+        assert_eq!(inspect_spk(pid, &spk).unwrap(), 1234);
+        assert!(generate_spk_for_spkid(MAX_SPKID + 1, pid).is_err());
     }
-    
-    generate_tskey(
-        pid,
-        &spkdata,
-        SPKCurve::gx(),
-        SPKCurve::gy(),
-        BigUint::from(SPKCurve::A),
-        SPKCurve::p(),
-        SPKCurve::n(),
-        SPKCurve::priv_key(),
-        1000,
-    )
 }
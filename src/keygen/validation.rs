@@ -1,60 +1,175 @@
 //! Key validation functions
 
-use crate::crypto::{bigint_to_bytes_le, bytes_to_bigint_le, decode_pkey, rc4_crypt, EllipticCurvePoint};
+use crate::crypto::{
+    bigint_to_bytes_le, bytes_to_bigint_le, ct_eq, decode_pkey, derive_rc4_key, pkey_len, rc4_crypt,
+    verify_pkey_format, KEY_LEN,
+};
 use crate::keygen::get_spkid;
+use crate::types::{CurveParams, KeyKind, LKPCurve, SPKCurve};
+use lru::LruCache;
 use num_bigint::BigUint;
 use sha1::{Digest, Sha1};
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
 
 /// Validate a Terminal Services key
+///
+/// A thin wrapper over `validate_tskey_detailed` for callers that only need a yes or no.
 pub fn validate_tskey(
     pid: &str,
     tskey: &str,
-    gx: BigUint,
-    gy: BigUint,
-    kx: BigUint,
-    ky: BigUint,
-    a: BigUint,
-    p: BigUint,
-    is_spk: bool,
+    curve: &CurveParams,
+    kind: KeyKind,
 ) -> anyhow::Result<bool> {
-    // Decode key
-    let keydata_int = decode_pkey(tskey)?;
-    let keydata_bytes = bigint_to_bytes_le(&keydata_int, 21);
-    
-    // Generate RC4 key from PID
-    let pid_utf16le = encode_utf16_le(pid);
-    let md5_digest = md5::compute(&pid_utf16le);
-    let mut rk = md5_digest[..5].to_vec();
-    rk.extend_from_slice(&[0u8; 11]);
-    
-    // Decrypt
-    let dc_kdata = rc4_crypt(&rk, &keydata_bytes);
-    
-    if dc_kdata.len() < 21 {
-        return Ok(false);
+    Ok(validate_tskey_detailed(pid, tskey, curve, kind)? == KeyDiagnosis::Valid)
+}
+
+/// Validate an LKP against the built-in LKP curve
+pub fn validate_lkp(pid: &str, lkp: &str) -> anyhow::Result<bool> {
+    validate_tskey(pid, lkp, &LKPCurve::params(), KeyKind::Lkp)
+}
+
+/// `validate_tskey` with a bounded cache of results, for callers that see the
+/// same keys repeatedly (e.g. the HTTP API)
+///
+/// Only successful checks are cached; decoding errors are recomputed every time.
+pub struct Validator {
+    spk_curve: CurveParams,
+    lkp_curve: CurveParams,
+    /// `(pid, key, kind)` -> validity, most recently used first
+    cache: Mutex<LruCache<(String, String, KeyKind), bool>>,
+}
+
+impl Validator {
+    /// Validator for the built-in curves remembering up to `capacity` results
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self::with_curves(SPKCurve::params(), LKPCurve::params(), capacity)
+    }
+
+    pub fn with_curves(spk_curve: CurveParams, lkp_curve: CurveParams, capacity: NonZeroUsize) -> Self {
+        Self {
+            spk_curve,
+            lkp_curve,
+            cache: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Validate an SPK or LKP, answering from the cache when possible
+    pub fn validate(&self, pid: &str, tskey: &str, kind: KeyKind) -> anyhow::Result<bool> {
+        let key = (pid.to_string(), tskey.to_string(), kind);
+        if let Some(&valid) = self.lock().get(&key) {
+            return Ok(valid);
+        }
+
+        // The lock is not held while validating so concurrent callers are not serialized
+        let curve = match kind {
+            KeyKind::Spk => &self.spk_curve,
+            KeyKind::Lkp => &self.lkp_curve,
+        };
+        let valid = validate_tskey(pid, tskey, curve, kind)?;
+        self.lock().put(key, valid);
+        Ok(valid)
+    }
+
+    /// Number of cached results
+    pub fn cached(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruCache<(String, String, KeyKind), bool>> {
+        // A panic while holding the lock cannot leave the cache inconsistent
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Outcome of checking a key, naming the stage at which validation failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyDiagnosis {
+    Valid,
+    /// The key could not be decoded (bad character, too long), or the PID has no SPKID
+    Malformed(String),
+    /// The key has only `len` of the `KEY_LEN` characters, e.g. from a truncated paste
+    TooShort { len: usize },
+    /// `h * K + s * G` is the point at infinity
+    PointAtInfinity,
+    /// The hash recomputed from the signature differs from the one in the key,
+    /// usually because the key belongs to another PID, curve or key kind
+    SignatureMismatch { h: u64, ht: u64 },
+    /// The signature is valid but the key was issued for a different SPKID
+    SpkidMismatch { key_spkid: u64, pid_spkid: u64 },
+}
+
+impl std::fmt::Display for KeyDiagnosis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Valid => write!(f, "key is valid"),
+            Self::Malformed(e) => write!(f, "key could not be decoded: {}", e),
+            Self::TooShort { len } => write!(f, "key is too short ({} characters, expected {})", len, KEY_LEN),
+            Self::PointAtInfinity => write!(f, "signature check reached the point at infinity"),
+            Self::SignatureMismatch { h, ht } => write!(
+                f,
+                "signature hash mismatch (key has {:#x}, recomputed {:#x}); wrong PID, curve or key type?",
+                h, ht
+            ),
+            Self::SpkidMismatch { key_spkid, pid_spkid } => write!(
+                f,
+                "SPKID mismatch (key carries {}, PID gives {})",
+                key_spkid, pid_spkid
+            ),
+        }
+    }
+}
+
+/// `validate_tskey_detailed` with decoding errors reported as `KeyDiagnosis::Malformed`
+pub fn diagnose_tskey(pid: &str, tskey: &str, curve: &CurveParams, kind: KeyKind) -> KeyDiagnosis {
+    validate_tskey_detailed(pid, tskey, curve, kind).unwrap_or_else(|e| KeyDiagnosis::Malformed(e.to_string()))
+}
+
+/// Check a key like `validate_tskey`, but report which check failed
+///
+/// Keys that cannot be decoded (bad characters, too long) are still errors; a short key
+/// is `KeyDiagnosis::TooShort`.
+pub fn validate_tskey_detailed(
+    pid: &str,
+    tskey: &str,
+    curve: &CurveParams,
+    kind: KeyKind,
+) -> anyhow::Result<KeyDiagnosis> {
+    check_tskey(pid, tskey, curve, kind == KeyKind::Spk)
+}
+
+/// `validate_tskey_detailed` with the SPKID check made explicit, for signing key data
+/// that was not derived from `pid`
+pub(crate) fn check_tskey(
+    pid: &str,
+    tskey: &str,
+    curve: &CurveParams,
+    check_spkid: bool,
+) -> anyhow::Result<KeyDiagnosis> {
+    let len = pkey_len(tskey)?;
+    if len < KEY_LEN {
+        return Ok(KeyDiagnosis::TooShort { len });
     }
     
-    let keydata_inner = &dc_kdata[..7];
-    let sigdata_bytes = &dc_kdata[7..];
-    let sigdata = bytes_to_bigint_le(sigdata_bytes);
+    let dc_kdata = decrypt_tskey(pid, tskey)?;
     
-    let h = &sigdata & BigUint::from(0x7FFFFFFFFFu64);
-    let s = (&sigdata >> 35) & BigUint::parse_bytes(b"1FFFFFFFFFFFFFFFFF", 16).unwrap();
+    let keydata_inner = &dc_kdata[..7];
+    let (s, h) = split_sigdata(&dc_kdata[7..]);
     
     // Verify signature
-    let g = EllipticCurvePoint::new(gx, gy, a.clone(), p.clone());
-    let k = EllipticCurvePoint::new(kx, ky, a, p);
+    let g = curve.generator();
+    let k = curve.public_key();
     
     let hk = k.mul(&h);
     let sg = g.mul(&s);
     let r = hk.add(&sg);
     
     if r.infinity {
-        return Ok(false);
+        return Ok(KeyDiagnosis::PointAtInfinity);
     }
     
-    let rx_bytes = bigint_to_bytes_le(&r.x, 48);
-    let ry_bytes = bigint_to_bytes_le(&r.y, 48);
+    let rx_bytes = bigint_to_bytes_le(&r.x, 48)?;
+    let ry_bytes = bigint_to_bytes_le(&r.y, 48)?;
     
     let mut sha1_input = keydata_inner.to_vec();
     sha1_input.extend_from_slice(&rx_bytes);
@@ -67,26 +182,164 @@ pub fn validate_tskey(
     let part2 = &part2_intermediate >> 29;
     let ht = (&part2 << 32) | &part1;
     
-    if h != ht {
-        return Ok(false);
+    // Constant-time comparisons of fixed-width encodings (see `ct_eq`)
+    if !ct_eq(&bigint_to_bytes_le(&h, 8)?, &bigint_to_bytes_le(&ht, 8)?) {
+        return Ok(KeyDiagnosis::SignatureMismatch {
+            h: to_u64(&h),
+            ht: to_u64(&ht),
+        });
     }
     
-    if is_spk {
+    if check_spkid {
         let spkid_from_key = bytes_to_bigint_le(keydata_inner) & BigUint::from(0x1FFFFFFFFFFu64);
         let spkid_from_pid = BigUint::from(get_spkid(pid)?);
-        return Ok(spkid_from_key == spkid_from_pid);
+        if !ct_eq(
+            &bigint_to_bytes_le(&spkid_from_key, 8)?,
+            &bigint_to_bytes_le(&spkid_from_pid, 8)?,
+        ) {
+            return Ok(KeyDiagnosis::SpkidMismatch {
+                key_spkid: to_u64(&spkid_from_key),
+                pid_spkid: to_u64(&spkid_from_pid),
+            });
+        }
     }
     
-    Ok(true)
+    Ok(KeyDiagnosis::Valid)
+}
+
+/// Signature components `(s, h)` carried by a key, for comparing against other
+/// implementations
+///
+/// Nothing is verified here; use `validate_tskey` for that.
+pub fn signature_components(pid: &str, tskey: &str) -> anyhow::Result<(BigUint, BigUint)> {
+    let dc_kdata = decrypt_tskey(pid, tskey)?;
+    Ok(split_sigdata(&dc_kdata[7..]))
+}
+
+/// Split the signature bytes (`s << 35 | h`) into `(s, h)`
+fn split_sigdata(sigdata_bytes: &[u8]) -> (BigUint, BigUint) {
+    let sigdata = bytes_to_bigint_le(sigdata_bytes);
+    let h = &sigdata & BigUint::from(0x7FFFFFFFFFu64);
+    let s = (&sigdata >> 35) & BigUint::parse_bytes(b"1FFFFFFFFFFFFFFFFF", 16).unwrap();
+    (s, h)
 }
 
-/// Encode string to UTF-16 LE bytes
-fn encode_utf16_le(s: &str) -> Vec<u8> {
-    let utf16: Vec<u16> = s.encode_utf16().collect();
-    let mut bytes = Vec::with_capacity(utf16.len() * 2);
-    for word in utf16 {
-        bytes.push((word & 0xFF) as u8);
-        bytes.push((word >> 8) as u8);
+/// Low 64 bits of `n`; every value reported in `KeyDiagnosis` fits
+fn to_u64(n: &BigUint) -> u64 {
+    n.to_u64_digits().first().copied().unwrap_or(0)
+}
+
+/// Decode a key and decrypt it with the PID-derived RC4 key
+///
+/// The result is always 21 bytes: the first 7 are the key data (SPKID or LKP info), the rest
+/// is the signature. Nothing is verified here.
+pub(crate) fn decrypt_tskey(pid: &str, tskey: &str) -> anyhow::Result<Vec<u8>> {
+    // Decode key, reporting typos by position rather than as a bad signature
+    verify_pkey_format(tskey)?;
+    let keydata_int = decode_pkey(tskey)?;
+    let keydata_bytes = bigint_to_bytes_le(&keydata_int, 21)?;
+    
+    rc4_crypt(&derive_rc4_key(pid), &keydata_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::{generate_lkp, generate_spk, generate_spk_for_spkid, generate_tskey_traced};
+    use crate::types::SPKCurve;
+    
+    #[test]
+    fn test_diagnose_tskey() {
+        let pid = "00490-92005-99454-AT527";
+        let curve = SPKCurve::params();
+        
+        let spk = generate_spk(pid).unwrap();
+        assert_eq!(diagnose_tskey(pid, &spk, &curve, KeyKind::Spk), KeyDiagnosis::Valid);
+        assert_eq!(diagnose_tskey(pid, "BCDF", &curve, KeyKind::Spk), KeyDiagnosis::TooShort { len: 4 });
+        assert!(matches!(diagnose_tskey(pid, "BCDF0", &curve, KeyKind::Spk), KeyDiagnosis::Malformed(_)));
+        assert!(matches!(
+            diagnose_tskey("00490-12345-67890-AT123", &spk, &curve, KeyKind::Spk),
+            KeyDiagnosis::SignatureMismatch { .. }
+        ));
+        
+        // Correctly signed for this PID, but carrying another SPKID
+        let other = generate_spk_for_spkid(7, pid).unwrap();
+        assert_eq!(
+            diagnose_tskey(pid, &other, &curve, KeyKind::Spk),
+            KeyDiagnosis::SpkidMismatch { key_spkid: 7, pid_spkid: 5 }
+        );
+    }
+    
+    #[test]
+    fn test_validate_tskey_detailed() {
+        let pid = "00490-92005-99454-AT527";
+        let curve = SPKCurve::params();
+        
+        let spk = generate_spk(pid).unwrap();
+        let short = &spk[..spk.len() - 6];
+        assert_eq!(validate_tskey_detailed(pid, &spk, &curve, KeyKind::Spk).unwrap(), KeyDiagnosis::Valid);
+        assert_eq!(validate_tskey_detailed(pid, short, &curve, KeyKind::Spk).unwrap(), KeyDiagnosis::TooShort { len: 30 });
+        assert_eq!(diagnose_tskey(pid, short, &curve, KeyKind::Spk), KeyDiagnosis::TooShort { len: 30 });
+        assert!(!validate_tskey(pid, short, &curve, KeyKind::Spk).unwrap());
+        assert!(matches!(
+            validate_tskey_detailed("00490-12345-67890-AT123", &spk, &curve, KeyKind::Spk).unwrap(),
+            KeyDiagnosis::SignatureMismatch { .. }
+        ));
+        
+        // Undecodable keys are errors, and `Malformed` from `diagnose_tskey`
+        for bad in ["BCDF0".to_string(), format!("{}B", spk)] {
+            assert!(validate_tskey_detailed(pid, &bad, &curve, KeyKind::Spk).is_err());
+            assert!(validate_tskey(pid, &bad, &curve, KeyKind::Spk).is_err());
+            assert!(matches!(diagnose_tskey(pid, &bad, &curve, KeyKind::Spk), KeyDiagnosis::Malformed(_)));
+        }
+    }
+    
+    #[test]
+    fn test_validate_lkp() {
+        let pid = "00490-92005-99454-AT527";
+        let lkp = generate_lkp(pid, 250, 29, 10, 2).unwrap();
+        assert!(validate_lkp(pid, &lkp).unwrap());
+        assert!(!validate_lkp("00490-12345-67890-AT123", &lkp).unwrap());
+        
+        // An SPK is signed on the other curve
+        let spk = generate_spk(pid).unwrap();
+        assert!(!validate_lkp(pid, &spk).unwrap());
+    }
+    
+    #[test]
+    fn test_validator_cache() {
+        let pid = "00490-92005-99454-AT527";
+        let spk = generate_spk(pid).unwrap();
+        let validator = Validator::new(NonZeroUsize::new(2).unwrap());
+        
+        // Miss, then hit
+        assert!(validator.validate(pid, &spk, KeyKind::Spk).unwrap());
+        assert!(validator.validate(pid, &spk, KeyKind::Spk).unwrap());
+        assert_eq!(validator.cached(), 1);
+        
+        // Errors are not cached
+        assert!(validator.validate(pid, "BCDF0", KeyKind::Spk).is_err());
+        assert_eq!(validator.cached(), 1);
+        
+        // The least recently used entry is evicted at capacity
+        assert!(!validator.validate(pid, &spk, KeyKind::Lkp).unwrap());
+        validator.validate(pid, &spk, KeyKind::Spk).unwrap();
+        let other = generate_spk(pid).unwrap();
+        assert!(validator.validate(pid, &other, KeyKind::Spk).unwrap());
+        assert_eq!(validator.cached(), 2);
+        let cache = validator.lock();
+        assert!(cache.contains(&(pid.to_string(), spk.clone(), KeyKind::Spk)));
+        assert!(!cache.contains(&(pid.to_string(), spk.clone(), KeyKind::Lkp)));
+    }
+    
+    #[test]
+    fn test_signature_components_match_trace() {
+        let pid = "00490-92005-99454-AT527";
+        let curve = SPKCurve::params();
+        let keydata = bigint_to_bytes_le(&BigUint::from(get_spkid(pid).unwrap()), 7).unwrap();
+        let trace = generate_tskey_traced(pid, &keydata, &curve, KeyKind::Spk, 100, &mut rand::thread_rng()).unwrap();
+        
+        let (s, h) = signature_components(pid, &trace.key).unwrap();
+        assert_eq!((s, h), (trace.s, trace.h));
     }
-    bytes
 }
@@ -0,0 +1,27 @@
+//! LyssaRDSGen core library
+//!
+//! Generation and validation of Service Provider Keys (SPKs) and License Key Packs (LKPs)
+//! for Microsoft Remote Desktop Services. The CLI, GUI and TUI front-ends live in the binary.
+//!
+//! Without the default `std` feature the crate is `no_std` + `alloc` and only the
+//! [`crypto`] module (curve arithmetic, `mod_inverse`, RC4, key encoding) is available.
+//! The `cdylib`/`staticlib` outputs still need `std` to link, so build the `no_std`
+//! library alone with `cargo rustc --lib --no-default-features --crate-type rlib`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod crypto;
+#[cfg(feature = "std")]
+pub mod export;
+#[cfg(feature = "std")]
+pub mod keygen;
+#[cfg(feature = "std")]
+pub mod types;
+
+#[cfg(feature = "cffi")]
+pub mod ffi;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
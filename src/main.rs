@@ -10,9 +10,6 @@
 )]
 
 mod cli;
-mod crypto;
-mod keygen;
-mod types;
 
 #[cfg(feature = "gui")]
 mod gui;
@@ -20,29 +17,38 @@ mod gui;
 #[cfg(feature = "tui")]
 mod tui;
 
+#[cfg(feature = "server")]
+mod server;
+
+#[cfg(any(feature = "gui", feature = "tui"))]
+mod ui;
+
 use std::env;
 
 fn main() {
     // Check if we should run GUI or TUI mode
     let args: Vec<String> = env::args().collect();
     
-    // Check for explicit --tui flag
-    let run_tui = args.contains(&"--tui".to_string());
+    // Check for explicit --tui flag or the tui subcommand
+    let run_tui = args.contains(&"--tui".to_string()) || args.get(1).is_some_and(|a| a == "tui");
     
     // Run GUI if:
     // 1. No arguments provided (just the program name)
     // 2. Only --gui flag is provided
     // 3. --gui flag is provided without other CLI arguments
+    // 4. Only the gui subcommand is provided
     let run_gui = !run_tui && (
         args.len() == 1 || 
-        (args.len() == 2 && args.contains(&"--gui".to_string())) ||
+        (args.len() == 2 && (args[1] == "--gui" || args[1] == "gui")) ||
         (args.contains(&"--gui".to_string()) && 
          !args.iter().any(|a| a.starts_with("--") && a != "--gui"))
     );
     
     #[cfg(feature = "tui")]
     if run_tui {
-        if let Err(e) = tui::run_tui() {
+        use clap::Parser;
+        let theme = cli::Cli::parse().theme;
+        if let Err(e) = tui::run_tui(theme) {
             eprintln!("TUI Error: {}", e);
             std::process::exit(1);
         }
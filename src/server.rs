@@ -0,0 +1,411 @@
+//! Headless HTTP JSON API, and the same operations over a Unix domain socket or Windows named pipe
+//!
+//! Endpoints (all `POST` with a JSON body):
+//! - `/spk` `{pid}` - generate an SPK
+//! - `/lkp` `{pid, count, license}` - generate an LKP
+//! - `/validate` `{pid, key, kind}` - validate an SPK (`kind: "spk"`) or LKP (`kind: "lkp"`)
+//!
+//! Bad input is answered with 400, bodies over `MAX_BODY_BYTES` with 413, and key generation
+//! failures (including timeouts) with 500.
+//!
+//! The socket mode (`--socket`) reads newline-delimited JSON requests naming the
+//! endpoint in `op`, e.g. `{"op":"spk","pid":"..."}`, and writes one JSON response
+//! line per request, in order. Failures are answered with `{"error": "..."}`. On Windows
+//! the path names a pipe, e.g. `\\.\pipe\lyssa`.
+
+use crate::cli::{KeyOutput, LkpOutput, ValidationOutput};
+use lyssa_rds_gen::keygen::{
+    derive_spkid, diagnose_tskey, generate_lkp_with_curve, generate_spk_with_curve, normalize_pid,
+    validate_pid, KeyDiagnosis, Validator, DEFAULT_MAX_ATTEMPTS,
+};
+use lyssa_rds_gen::types::{CurveParams, KeyKind, LKPCurve, LicenseInfo, SPKCurve};
+use interprocess::local_socket::{prelude::*, GenericFilePath, Listener, ListenerOptions, Stream};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+use tiny_http::{Header, Method, Response, Server};
+
+/// Number of `/validate` results remembered across requests
+const VALIDATION_CACHE_SIZE: usize = 1024;
+
+/// Largest accepted HTTP request body; every valid request is far smaller
+const MAX_BODY_BYTES: u64 = 64 * 1024;
+
+#[derive(Deserialize)]
+struct SpkRequest {
+    pid: String,
+}
+
+#[derive(Deserialize)]
+struct LkpRequest {
+    pid: String,
+    count: u32,
+    license: String,
+}
+
+#[derive(Deserialize)]
+struct ValidateRequest {
+    pid: String,
+    key: String,
+    #[serde(default = "default_kind")]
+    kind: String,
+}
+
+fn default_kind() -> String {
+    "spk".to_string()
+}
+
+/// One line of socket input: an endpoint's request body tagged with its name
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum SocketRequest {
+    Spk(SpkRequest),
+    Lkp(LkpRequest),
+    Validate(ValidateRequest),
+}
+
+/// Error response: HTTP status code and message
+struct ApiError(u16, String);
+
+impl ApiError {
+    fn bad_request(e: impl std::fmt::Display) -> Self {
+        Self(400, e.to_string())
+    }
+
+    fn failed(e: impl std::fmt::Display) -> Self {
+        Self(500, e.to_string())
+    }
+}
+
+/// State shared by all requests
+struct Api {
+    validator: Validator,
+    /// Per-key generation time limit
+    timeout: Option<Duration>,
+}
+
+impl Api {
+    fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            validator: Validator::new(NonZeroUsize::new(VALIDATION_CACHE_SIZE).unwrap()),
+            timeout,
+        }
+    }
+}
+
+/// Serve the API on `addr` until the process is killed
+pub fn run_server(addr: &str, timeout: Option<Duration>) -> anyhow::Result<()> {
+    let server = Server::http(addr).map_err(|e| anyhow::anyhow!("Failed to bind {}: {}", addr, e))?;
+    eprintln!("Listening on http://{}", addr);
+
+    // Key generation is CPU-bound, so one worker per core; further requests wait in the queue
+    let workers = std::thread::available_parallelism().map_or(4, NonZeroUsize::get);
+    let server = Arc::new(server);
+    let api = Arc::new(Api::new(timeout));
+    let handles: Vec<_> = (0..workers)
+        .map(|_| {
+            let server = Arc::clone(&server);
+            let api = Arc::clone(&api);
+            std::thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    serve_request(&api, request);
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        let _ = handle.join();
+    }
+    Ok(())
+}
+
+fn serve_request(api: &Api, mut request: tiny_http::Request) {
+    let (status, value) = match read_body(request.as_reader()) {
+        Ok(body) => respond(api, request.method(), request.url(), &body),
+        Err(ApiError(status, message)) => (status, json!({ "error": message })),
+    };
+
+    let header = Header::from_bytes("Content-Type", "application/json").expect("valid header");
+    let response = Response::from_string(value.to_string())
+        .with_status_code(status)
+        .with_header(header);
+    if let Err(e) = request.respond(response) {
+        eprintln!("Failed to send response: {}", e);
+    }
+}
+
+/// Read a UTF-8 request body of at most `MAX_BODY_BYTES`
+fn read_body(reader: impl Read) -> Result<String, ApiError> {
+    let mut body = Vec::new();
+    reader
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| ApiError::bad_request(format!("Failed to read body: {}", e)))?;
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return Err(ApiError(413, format!("Request body exceeds {} bytes", MAX_BODY_BYTES)));
+    }
+    String::from_utf8(body).map_err(|_| ApiError::bad_request("Request body is not UTF-8"))
+}
+
+/// Serve newline-delimited JSON requests on a Unix domain socket (a named pipe on
+/// Windows) at `path` until the process is killed
+pub fn run_socket(path: &str, timeout: Option<Duration>) -> anyhow::Result<()> {
+    let listener = bind_socket(path).map_err(|e| anyhow::anyhow!("Failed to bind {}: {}", path, e))?;
+    eprintln!("Listening on {}", path);
+    serve_socket(listener, Arc::new(Api::new(timeout)));
+    Ok(())
+}
+
+/// Listen on `path`, replacing a socket file left behind by a server that crashed
+///
+/// A socket that still accepts connections is left alone, so a running server is never displaced.
+fn bind_socket(path: &str) -> std::io::Result<Listener> {
+    let listen = || ListenerOptions::new().name(path.to_fs_name::<GenericFilePath>()?).create_sync();
+    match listen() {
+        // Only Unix sockets leave a file behind; named pipes disappear with their server
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse && cfg!(unix) && connect_socket(path).is_err() => {
+            std::fs::remove_file(path)?;
+            listen()
+        }
+        result => result,
+    }
+}
+
+fn connect_socket(path: &str) -> std::io::Result<Stream> {
+    Stream::connect(path.to_fs_name::<GenericFilePath>()?)
+}
+
+/// Answer every connection on its own thread, since a client may keep its connection open
+fn serve_socket(listener: Listener, api: Arc<Api>) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        let api = Arc::clone(&api);
+        std::thread::spawn(move || {
+            if let Err(e) = serve_lines(&api, BufReader::new(&stream), &stream) {
+                eprintln!("Connection closed: {}", e);
+            }
+        });
+    }
+}
+
+/// Answer each non-empty request line with one response line until `reader` ends
+fn serve_lines(api: &Api, reader: impl BufRead, mut writer: impl Write) -> std::io::Result<()> {
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        writeln!(writer, "{}", respond_line(api, &line))?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// Handle one socket request line, returning the JSON response
+fn respond_line(api: &Api, line: &str) -> Value {
+    let result = parse(line).and_then(|req| match req {
+        SocketRequest::Spk(req) => handle_spk(api, req),
+        SocketRequest::Lkp(req) => handle_lkp(api, req),
+        SocketRequest::Validate(req) => handle_validate(&api.validator, req),
+    });
+
+    match result {
+        Ok(value) => value,
+        Err(ApiError(_, message)) => json!({ "error": message }),
+    }
+}
+
+/// Route a request and return the status code and JSON body
+fn respond(api: &Api, method: &Method, url: &str, body: &str) -> (u16, Value) {
+    let result = match url {
+        "/spk" | "/lkp" | "/validate" if *method != Method::Post => {
+            Err(ApiError(405, format!("{} only accepts POST", url)))
+        }
+        "/spk" => parse(body).and_then(|req| handle_spk(api, req)),
+        "/lkp" => parse(body).and_then(|req| handle_lkp(api, req)),
+        "/validate" => parse(body).and_then(|req| handle_validate(&api.validator, req)),
+        _ => Err(ApiError(404, format!("No such endpoint: {}", url))),
+    };
+
+    match result {
+        Ok(value) => (200, value),
+        Err(ApiError(status, message)) => (status, json!({ "error": message })),
+    }
+}
+
+fn parse<'a, T: Deserialize<'a>>(body: &'a str) -> Result<T, ApiError> {
+    serde_json::from_str(body).map_err(ApiError::bad_request)
+}
+
+fn checked_pid(pid: &str) -> Result<String, ApiError> {
+    let pid = normalize_pid(pid);
+    validate_pid(&pid).map_err(ApiError::bad_request)?;
+    Ok(pid)
+}
+
+fn handle_spk(api: &Api, req: SpkRequest) -> Result<Value, ApiError> {
+    let pid = checked_pid(&req.pid)?;
+    let spkid = derive_spkid(&pid).map_err(ApiError::bad_request)?.spkid;
+    let spk = generate_spk_with_curve(&pid, &SPKCurve::params(), DEFAULT_MAX_ATTEMPTS, api.timeout)
+        .map_err(ApiError::failed)?;
+    Ok(json!(KeyOutput {
+        pid,
+        spkid: Some(spkid),
+        spk: Some(spk),
+        lkp: None,
+    }))
+}
+
+fn handle_lkp(api: &Api, req: LkpRequest) -> Result<Value, ApiError> {
+    let pid = checked_pid(&req.pid)?;
+    let license_info = LicenseInfo::parse(&req.license).map_err(ApiError::bad_request)?;
+    if !(1..=9999).contains(&req.count) {
+        return Err(ApiError::bad_request("License count must be between 1 and 9999"));
+    }
+
+    let lkp = generate_lkp_with_curve(
+        &pid,
+        req.count,
+        license_info.chid,
+        license_info.major_ver,
+        license_info.minor_ver,
+        &LKPCurve::params(),
+        DEFAULT_MAX_ATTEMPTS,
+        api.timeout,
+    )
+    .map_err(ApiError::failed)?;
+
+    Ok(json!(KeyOutput {
+        pid,
+        spkid: None,
+        spk: None,
+        lkp: Some(LkpOutput {
+            license: req.license,
+            description: license_info.description,
+            count: req.count,
+            lkp,
+        }),
+    }))
+}
+
+fn handle_validate(validator: &Validator, req: ValidateRequest) -> Result<Value, ApiError> {
+    let pid = checked_pid(&req.pid)?;
+    let kind = req.kind.parse::<KeyKind>().map_err(ApiError::bad_request)?;
+    let valid = validator
+        .validate(&pid, &req.key, kind)
+        .map_err(ApiError::bad_request)?;
+
+    // Only the verdict is cached, so the reason is worked out again for rejected keys
+    let diagnosis = if valid {
+        KeyDiagnosis::Valid
+    } else {
+        diagnose_tskey(&pid, &req.key, &CurveParams::for_kind(kind), kind)
+    };
+    Ok(json!(ValidationOutput::new(&pid, kind, &diagnosis)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_respond_status_codes() {
+        let pid = "00490-92005-99454-AT527";
+        let api = Api {
+            validator: Validator::new(NonZeroUsize::new(8).unwrap()),
+            timeout: None,
+        };
+
+        let (status, value) = respond(&api, &Method::Post, "/spk", &json!({ "pid": pid }).to_string());
+        assert_eq!(status, 200);
+        let spk = value["spk"].as_str().unwrap().to_string();
+
+        let body = json!({ "pid": pid, "key": spk, "kind": "spk" }).to_string();
+        let (status, value) = respond(&api, &Method::Post, "/validate", &body);
+        assert_eq!((status, value["valid"].as_bool()), (200, Some(true)));
+
+        let body = json!({ "pid": pid, "count": 0, "license": "029_10_2" }).to_string();
+        assert_eq!(respond(&api, &Method::Post, "/lkp", &body).0, 400);
+        assert_eq!(respond(&api, &Method::Post, "/spk", "not json").0, 400);
+        assert_eq!(respond(&api, &Method::Post, "/spk", r#"{"pid": "bad"}"#).0, 400);
+        assert_eq!(respond(&api, &Method::Get, "/spk", "").0, 405);
+        assert_eq!(respond(&api, &Method::Post, "/nope", "").0, 404);
+
+        let body = json!({ "pid": pid, "key": spk, "kind": "LKP" }).to_string();
+        let (status, value) = respond(&api, &Method::Post, "/validate", &body);
+        assert_eq!((status, value["kind"].as_str(), value["valid"].as_bool()), (200, Some("lkp"), Some(false)));
+        assert!(value["reason"].as_str().unwrap().contains("signature hash mismatch"));
+        let body = json!({ "pid": pid, "key": spk, "kind": "tsk" }).to_string();
+        assert_eq!(respond(&api, &Method::Post, "/validate", &body).0, 400);
+    }
+
+    #[test]
+    fn test_read_body_limit() {
+        let max = MAX_BODY_BYTES as usize;
+        assert_eq!(read_body(&b"{}"[..]).ok().as_deref(), Some("{}"));
+        assert_eq!(read_body(&vec![b' '; max][..]).map(|body| body.len()).ok(), Some(max));
+        assert!(matches!(read_body(&vec![b' '; max + 1][..]), Err(ApiError(413, _))));
+        assert!(matches!(read_body(&[0xff, 0xfe][..]), Err(ApiError(400, _))));
+    }
+
+    /// A socket path unique to this test run; a pipe name on Windows
+    fn socket_path(name: &str) -> String {
+        if cfg!(windows) {
+            format!(r"\\.\pipe\lyssa_rds_gen_{}_{}", name, std::process::id())
+        } else {
+            let path = std::env::temp_dir().join(format!("lyssa_rds_gen_{}_{}.sock", name, std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            path.to_string_lossy().into_owned()
+        }
+    }
+
+    #[test]
+    fn test_socket_exchange() {
+        let path = socket_path("exchange");
+        let listener = bind_socket(&path).unwrap();
+        std::thread::spawn(move || serve_socket(listener, Arc::new(Api::new(None))));
+
+        let pid = "00490-92005-99454-AT527";
+        let stream = connect_socket(&path).unwrap();
+        writeln!(&stream, "{}", json!({ "op": "spk", "pid": pid })).unwrap();
+        writeln!(&stream, r#"{{"op": "nope"}}"#).unwrap();
+
+        let mut lines = BufReader::new(&stream).lines();
+        let mut next = || serde_json::from_str::<Value>(&lines.next().unwrap().unwrap()).unwrap();
+        let response = next();
+        assert_eq!(response["pid"], pid);
+        let spk = response["spk"].as_str().unwrap();
+        assert!(Validator::new(NonZeroUsize::new(1).unwrap()).validate(pid, spk, KeyKind::Spk).unwrap());
+        assert!(next()["error"].as_str().unwrap().contains("nope"));
+
+        // The listener lives on in its thread, so its file is not reclaimed
+        if cfg!(unix) {
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bind_socket_replaces_stale_file() {
+        let path = socket_path("stale");
+
+        // std's listener leaves its file behind when dropped, like a crashed server
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(std::path::Path::new(&path).exists());
+        let listener = bind_socket(&path).unwrap();
+
+        // A live socket is not taken over
+        assert!(bind_socket(&path).is_err());
+        drop(listener);
+    }
+}
@@ -1,26 +1,120 @@
 //! Terminal User Interface
 
-use crate::keygen::{generate_lkp, generate_spk, validate_tskey};
-use crate::types::{LicenseInfo, SPKCurve, LICENSE_TYPES};
+use lyssa_rds_gen::keygen::{
+    diagnose_tskey, generate_lkp, generate_spk, get_spkid, normalize_pid, validate_pid,
+};
+use lyssa_rds_gen::types::{KeyKind, LKPCurve, LicenseInfo, SPKCurve, LICENSE_TYPES};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    clipboard::CopyToClipboard,
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyEventKind, MouseButton, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use num_bigint::BigUint;
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Position, Rect},
     style::{Color, Modifier, Style},
-    text::{Line, Span, Text},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Clear, List, ListItem, ListState, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Wrap,
+    },
     Frame, Terminal,
 };
+use crate::cli::TuiTheme;
+use crate::ui::validation_status;
+use clap::ValueEnum;
 use std::io;
+use std::sync::mpsc;
+
+/// Colors used throughout `ui`, selected with `--theme` and cycled with F3
+#[derive(Clone, Copy)]
+struct Theme {
+    name: TuiTheme,
+    title: Color,
+    /// Border of the focused widget and keys in the help popup
+    focus: Color,
+    valid: Color,
+    warning: Color,
+    error: Color,
+    /// Background of the selected license
+    highlight: Color,
+    generate_spk: Color,
+    /// Both validate buttons
+    validate: Color,
+    generate_lkp: Color,
+    /// Text on a focused (filled) button
+    button_text: Color,
+    output: Color,
+    help: Color,
+}
+
+impl Theme {
+    fn get(name: TuiTheme) -> Self {
+        match name {
+            TuiTheme::Default => Self {
+                name,
+                title: Color::Cyan,
+                focus: Color::Yellow,
+                valid: Color::Green,
+                warning: Color::LightYellow,
+                error: Color::Red,
+                highlight: Color::DarkGray,
+                generate_spk: Color::Green,
+                validate: Color::Blue,
+                generate_lkp: Color::Cyan,
+                button_text: Color::Black,
+                output: Color::Green,
+                help: Color::Gray,
+            },
+            TuiTheme::Mono => Self {
+                name,
+                title: Color::White,
+                focus: Color::White,
+                valid: Color::Gray,
+                warning: Color::Gray,
+                error: Color::White,
+                highlight: Color::DarkGray,
+                generate_spk: Color::Gray,
+                validate: Color::Gray,
+                generate_lkp: Color::Gray,
+                button_text: Color::Black,
+                output: Color::White,
+                help: Color::Gray,
+            },
+            TuiTheme::HighContrast => Self {
+                name,
+                title: Color::White,
+                focus: Color::LightYellow,
+                valid: Color::LightGreen,
+                warning: Color::LightMagenta,
+                error: Color::LightRed,
+                highlight: Color::Blue,
+                generate_spk: Color::LightGreen,
+                validate: Color::LightCyan,
+                generate_lkp: Color::LightMagenta,
+                button_text: Color::Black,
+                output: Color::White,
+                help: Color::White,
+            },
+        }
+    }
+
+    /// The theme after this one in `TuiTheme` order, wrapping around
+    fn next(self) -> Self {
+        let themes = TuiTheme::value_variants();
+        let idx = themes.iter().position(|&t| t == self.name).unwrap_or(0);
+        Self::get(themes[(idx + 1) % themes.len()])
+    }
+}
 
 enum InputField {
     Pid,
     Spk,
+    Lkp,
     Count,
     License,
 }
@@ -29,40 +123,228 @@ enum FocusedWidget {
     Input(InputField),
     GenerateSpk,
     ValidateSpk,
+    ValidateLkp,
     GenerateLkp,
+    SpkOutput,
+    LkpOutput,
+}
+
+/// Vertical scroll position of an output panel, clamped to the content on each draw
+#[derive(Default)]
+struct OutputScroll {
+    offset: u16,
+    /// Largest useful offset for the current panel size
+    max: u16,
+}
+
+impl OutputScroll {
+    fn scroll_up(&mut self, lines: u16) {
+        self.offset = self.offset.saturating_sub(lines);
+    }
+
+    fn scroll_down(&mut self, lines: u16) {
+        self.offset = self.offset.saturating_add(lines).min(self.max);
+    }
+
+    /// Recompute `max` for `text` wrapped into `area` (including borders)
+    fn fit(&mut self, text: &str, area: Rect) {
+        let width = area.width.saturating_sub(2).max(1) as usize;
+        let height = area.height.saturating_sub(2) as usize;
+        let lines: usize = text
+            .lines()
+            .map(|line| line.chars().count().max(1).div_ceil(width))
+            .sum();
+        self.max = lines.saturating_sub(height) as u16;
+        self.offset = self.offset.min(self.max);
+    }
+}
+
+/// Single-line text input with a cursor position (counted in chars)
+struct TextInput {
+    value: String,
+    cursor: usize,
+}
+
+impl TextInput {
+    fn new(value: &str) -> Self {
+        Self {
+            value: value.to_string(),
+            cursor: value.chars().count(),
+        }
+    }
+
+    fn byte_index(&self) -> usize {
+        self.value
+            .char_indices()
+            .nth(self.cursor)
+            .map(|(i, _)| i)
+            .unwrap_or(self.value.len())
+    }
+
+    fn insert(&mut self, c: char) {
+        let idx = self.byte_index();
+        self.value.insert(idx, c);
+        self.cursor += 1;
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            let idx = self.byte_index();
+            self.value.remove(idx);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.value.chars().count() {
+            let idx = self.byte_index();
+            self.value.remove(idx);
+        }
+    }
+
+    fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    fn move_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.value.chars().count());
+    }
+
+    fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    fn move_end(&mut self) {
+        self.cursor = self.value.chars().count();
+    }
+
+    /// Render the value, showing the cursor as a reversed character when focused
+    fn render(&self, focused: bool) -> Line<'_> {
+        if !focused {
+            return Line::from(self.value.as_str());
+        }
+
+        let (before, rest) = self.value.split_at(self.byte_index());
+        let mut after = rest.chars();
+        let at_cursor = after.next().map(String::from).unwrap_or_else(|| " ".to_string());
+
+        Line::from(vec![
+            Span::raw(before),
+            Span::styled(at_cursor, Style::default().add_modifier(Modifier::REVERSED)),
+            Span::raw(after.as_str()),
+        ])
+    }
+}
+
+/// Screen areas of clickable widgets, recorded on each draw for mouse hit-testing
+#[derive(Default)]
+struct ClickAreas {
+    pid: Rect,
+    spk: Rect,
+    lkp: Rect,
+    count: Rect,
+    license: Rect,
+    generate_spk: Rect,
+    validate_spk: Rect,
+    validate_lkp: Rect,
+    generate_lkp: Rect,
+    spk_output: Rect,
+    lkp_output: Rect,
+}
+
+/// Frames of the status bar spinner shown while a key is generated
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// What the worker thread sends back once generation finishes
+enum JobResult {
+    Spk(anyhow::Result<String>),
+    Lkp(anyhow::Result<String>, LicenseInfo),
 }
 
 pub struct TuiApp {
-    pid: String,
-    spk: String,
-    count: String,
+    pid: TextInput,
+    spk: TextInput,
+    lkp: TextInput,
+    count: TextInput,
     license_state: ListState,
+    license_query: String,
+    license_searching: bool,
+    /// Selection to restore if a search is cancelled
+    license_before_search: Option<usize>,
     generated_spk: String,
     generated_lkp: String,
+    /// License description of `generated_lkp`
+    generated_lkp_description: String,
+    /// Kind of the most recent successful generation, copied by `c`
+    last_generated: Option<KeyKind>,
+    /// `(label, text)` to copy on the next loop iteration, once the terminal is at hand
+    pending_copy: Option<(&'static str, String)>,
+    /// System clipboard, if one could be opened; OSC 52 is used otherwise
+    #[cfg(feature = "clipboard")]
+    clipboard: Option<arboard::Clipboard>,
+    spk_scroll: OutputScroll,
+    lkp_scroll: OutputScroll,
     status_message: String,
+    /// Generation running on a worker thread, if any
+    job: Option<mpsc::Receiver<JobResult>>,
+    /// Advanced every loop iteration to animate `SPINNER`
+    spinner_frame: usize,
     focused: FocusedWidget,
+    click_areas: ClickAreas,
+    /// Keybinding overlay toggled with `?`
+    show_help: bool,
+    theme: Theme,
     should_quit: bool,
 }
 
 impl TuiApp {
-    fn new() -> Self {
+    fn new(theme: TuiTheme) -> Self {
         let mut license_state = ListState::default();
         license_state.select(Some(18)); // Default to Windows Server 2022 Per Device
         
         Self {
-            pid: String::new(),
-            spk: String::new(),
-            count: String::from("1"),
+            pid: TextInput::new(""),
+            spk: TextInput::new(""),
+            lkp: TextInput::new(""),
+            count: TextInput::new("1"),
             license_state,
+            license_query: String::new(),
+            license_searching: false,
+            license_before_search: None,
             generated_spk: String::new(),
             generated_lkp: String::new(),
+            generated_lkp_description: String::new(),
+            last_generated: None,
+            pending_copy: None,
+            #[cfg(feature = "clipboard")]
+            clipboard: arboard::Clipboard::new().ok(),
+            spk_scroll: OutputScroll::default(),
+            lkp_scroll: OutputScroll::default(),
             status_message: String::new(),
+            job: None,
+            spinner_frame: 0,
             focused: FocusedWidget::Input(InputField::Pid),
+            click_areas: ClickAreas::default(),
+            show_help: false,
+            theme: Theme::get(theme),
             should_quit: false,
         }
     }
 
     fn handle_key(&mut self, key: KeyCode) {
+        // The help overlay swallows keys until it is dismissed
+        if self.show_help {
+            if matches!(key, KeyCode::Esc | KeyCode::Char('?') | KeyCode::Char('q')) {
+                self.show_help = false;
+            }
+            return;
+        }
+
+        if self.license_searching {
+            self.handle_search_key(key);
+            return;
+        }
+
         match key {
             KeyCode::Esc | KeyCode::Char('q') => {
                 self.should_quit = true;
@@ -76,82 +358,300 @@ impl TuiApp {
             KeyCode::Enter => {
                 self.handle_enter();
             }
+            KeyCode::F(2) => {
+                self.copy_all();
+            }
+            // `c` is text in the PID, SPK and LKP fields
+            KeyCode::Char('c')
+                if !matches!(self.focused, FocusedWidget::Input(InputField::Pid | InputField::Spk | InputField::Lkp)) =>
+            {
+                self.copy_key();
+            }
+            KeyCode::F(3) => {
+                self.theme = self.theme.next();
+                if let Some(name) = self.theme.name.to_possible_value() {
+                    self.status_message = format!("Theme: {}", name.get_name());
+                }
+            }
+            KeyCode::Char('j') if self.focused_output().is_some() => {
+                if let Some(scroll) = self.focused_output() {
+                    scroll.scroll_down(1);
+                }
+            }
+            KeyCode::Char('k') if self.focused_output().is_some() => {
+                if let Some(scroll) = self.focused_output() {
+                    scroll.scroll_up(1);
+                }
+            }
+            KeyCode::Char('/') if matches!(self.focused, FocusedWidget::Input(InputField::License)) => {
+                self.start_license_search();
+            }
+            KeyCode::Char('?') => {
+                self.show_help = true;
+            }
             KeyCode::Char(c) => {
                 self.handle_char(c);
             }
             KeyCode::Backspace => {
                 self.handle_backspace();
             }
+            KeyCode::Delete => {
+                if let Some(input) = self.focused_input() {
+                    input.delete();
+                }
+            }
+            KeyCode::Left => {
+                if let Some(input) = self.focused_input() {
+                    input.move_left();
+                }
+            }
+            KeyCode::Right => {
+                if let Some(input) = self.focused_input() {
+                    input.move_right();
+                }
+            }
+            KeyCode::Home => {
+                if let Some(input) = self.focused_input() {
+                    input.move_home();
+                }
+            }
+            KeyCode::End => {
+                if let Some(input) = self.focused_input() {
+                    input.move_end();
+                }
+            }
             KeyCode::Up => {
                 if matches!(self.focused, FocusedWidget::Input(InputField::License)) {
                     self.prev_license();
+                } else if let Some(scroll) = self.focused_output() {
+                    scroll.scroll_up(1);
                 }
             }
             KeyCode::Down => {
                 if matches!(self.focused, FocusedWidget::Input(InputField::License)) {
                     self.next_license();
+                } else if let Some(scroll) = self.focused_output() {
+                    scroll.scroll_down(1);
+                }
+            }
+            KeyCode::PageUp => {
+                if let Some(scroll) = self.focused_output() {
+                    scroll.scroll_up(5);
+                }
+            }
+            KeyCode::PageDown => {
+                if let Some(scroll) = self.focused_output() {
+                    scroll.scroll_down(5);
                 }
             }
             _ => {}
         }
     }
 
+    fn handle_click(&mut self, column: u16, row: u16) {
+        if self.show_help {
+            self.show_help = false;
+            return;
+        }
+
+        if self.license_searching {
+            self.finish_license_search(self.selected_license());
+        }
+
+        let pos = Position::new(column, row);
+        let areas = &self.click_areas;
+
+        if areas.pid.contains(pos) {
+            self.focused = FocusedWidget::Input(InputField::Pid);
+        } else if areas.spk.contains(pos) {
+            self.focused = FocusedWidget::Input(InputField::Spk);
+        } else if areas.lkp.contains(pos) {
+            self.focused = FocusedWidget::Input(InputField::Lkp);
+        } else if areas.count.contains(pos) {
+            self.focused = FocusedWidget::Input(InputField::Count);
+        } else if areas.license.contains(pos) {
+            self.focused = FocusedWidget::Input(InputField::License);
+        } else if areas.generate_spk.contains(pos) {
+            self.focused = FocusedWidget::GenerateSpk;
+            self.generate_spk();
+        } else if areas.validate_spk.contains(pos) {
+            self.focused = FocusedWidget::ValidateSpk;
+            self.validate_spk();
+        } else if areas.validate_lkp.contains(pos) {
+            self.focused = FocusedWidget::ValidateLkp;
+            self.validate_lkp();
+        } else if areas.generate_lkp.contains(pos) {
+            self.focused = FocusedWidget::GenerateLkp;
+            self.generate_lkp();
+        } else if areas.spk_output.contains(pos) {
+            self.focused = FocusedWidget::SpkOutput;
+        } else if areas.lkp_output.contains(pos) {
+            self.focused = FocusedWidget::LkpOutput;
+        }
+    }
+
+    /// Scroll whichever output panel is under the mouse
+    fn handle_wheel(&mut self, column: u16, row: u16, down: bool) {
+        let pos = Position::new(column, row);
+        let scroll = if self.click_areas.spk_output.contains(pos) {
+            &mut self.spk_scroll
+        } else if self.click_areas.lkp_output.contains(pos) {
+            &mut self.lkp_scroll
+        } else {
+            return;
+        };
+
+        if down {
+            scroll.scroll_down(1);
+        } else {
+            scroll.scroll_up(1);
+        }
+    }
+
+    fn focused_output(&mut self) -> Option<&mut OutputScroll> {
+        match self.focused {
+            FocusedWidget::SpkOutput => Some(&mut self.spk_scroll),
+            FocusedWidget::LkpOutput => Some(&mut self.lkp_scroll),
+            _ => None,
+        }
+    }
+
     fn next_field(&mut self) {
         self.focused = match self.focused {
             FocusedWidget::Input(InputField::Pid) => FocusedWidget::Input(InputField::Spk),
-            FocusedWidget::Input(InputField::Spk) => FocusedWidget::Input(InputField::Count),
+            FocusedWidget::Input(InputField::Spk) => FocusedWidget::Input(InputField::Lkp),
+            FocusedWidget::Input(InputField::Lkp) => FocusedWidget::Input(InputField::Count),
             FocusedWidget::Input(InputField::Count) => FocusedWidget::Input(InputField::License),
             FocusedWidget::Input(InputField::License) => FocusedWidget::GenerateSpk,
             FocusedWidget::GenerateSpk => FocusedWidget::ValidateSpk,
-            FocusedWidget::ValidateSpk => FocusedWidget::GenerateLkp,
-            FocusedWidget::GenerateLkp => FocusedWidget::Input(InputField::Pid),
+            FocusedWidget::ValidateSpk => FocusedWidget::ValidateLkp,
+            FocusedWidget::ValidateLkp => FocusedWidget::GenerateLkp,
+            FocusedWidget::GenerateLkp => FocusedWidget::SpkOutput,
+            FocusedWidget::SpkOutput => FocusedWidget::LkpOutput,
+            FocusedWidget::LkpOutput => FocusedWidget::Input(InputField::Pid),
         };
     }
 
     fn prev_field(&mut self) {
         self.focused = match self.focused {
-            FocusedWidget::Input(InputField::Pid) => FocusedWidget::GenerateLkp,
+            FocusedWidget::Input(InputField::Pid) => FocusedWidget::LkpOutput,
             FocusedWidget::Input(InputField::Spk) => FocusedWidget::Input(InputField::Pid),
-            FocusedWidget::Input(InputField::Count) => FocusedWidget::Input(InputField::Spk),
+            FocusedWidget::Input(InputField::Lkp) => FocusedWidget::Input(InputField::Spk),
+            FocusedWidget::Input(InputField::Count) => FocusedWidget::Input(InputField::Lkp),
             FocusedWidget::Input(InputField::License) => FocusedWidget::Input(InputField::Count),
             FocusedWidget::GenerateSpk => FocusedWidget::Input(InputField::License),
             FocusedWidget::ValidateSpk => FocusedWidget::GenerateSpk,
-            FocusedWidget::GenerateLkp => FocusedWidget::ValidateSpk,
+            FocusedWidget::ValidateLkp => FocusedWidget::ValidateSpk,
+            FocusedWidget::GenerateLkp => FocusedWidget::ValidateLkp,
+            FocusedWidget::SpkOutput => FocusedWidget::GenerateLkp,
+            FocusedWidget::LkpOutput => FocusedWidget::SpkOutput,
         };
     }
 
+    fn focused_input(&mut self) -> Option<&mut TextInput> {
+        match &self.focused {
+            FocusedWidget::Input(InputField::Pid) => Some(&mut self.pid),
+            FocusedWidget::Input(InputField::Spk) => Some(&mut self.spk),
+            FocusedWidget::Input(InputField::Lkp) => Some(&mut self.lkp),
+            FocusedWidget::Input(InputField::Count) => Some(&mut self.count),
+            _ => None,
+        }
+    }
+
     fn handle_char(&mut self, c: char) {
         match &self.focused {
-            FocusedWidget::Input(InputField::Pid) => self.pid.push(c),
-            FocusedWidget::Input(InputField::Spk) => self.spk.push(c),
-            FocusedWidget::Input(InputField::Count) => {
-                if c.is_ascii_digit() {
-                    self.count.push(c);
-                }
-            }
+            FocusedWidget::Input(InputField::Pid) => self.pid.insert(c),
+            FocusedWidget::Input(InputField::Spk) => self.spk.insert(c),
+            FocusedWidget::Input(InputField::Lkp) => self.lkp.insert(c),
+            FocusedWidget::Input(InputField::Count) if c.is_ascii_digit() => self.count.insert(c),
             _ => {}
         }
     }
 
+    fn handle_paste(&mut self, text: &str) {
+        for c in text.chars().filter(|c| !c.is_control()) {
+            self.handle_char(c);
+        }
+    }
+
     fn handle_backspace(&mut self) {
-        match &self.focused {
-            FocusedWidget::Input(InputField::Pid) => {
-                self.pid.pop();
+        if let Some(input) = self.focused_input() {
+            input.backspace();
+        }
+    }
+
+    fn handle_search_key(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Esc => {
+                self.finish_license_search(self.license_before_search);
+            }
+            KeyCode::Enter => {
+                self.finish_license_search(self.selected_license());
             }
-            FocusedWidget::Input(InputField::Spk) => {
-                self.spk.pop();
+            KeyCode::Char(c) => {
+                self.license_query.push(c);
+                self.reset_license_selection();
             }
-            FocusedWidget::Input(InputField::Count) => {
-                self.count.pop();
+            KeyCode::Backspace => {
+                self.license_query.pop();
+                self.reset_license_selection();
             }
+            KeyCode::Up => self.prev_license(),
+            KeyCode::Down => self.next_license(),
             _ => {}
         }
     }
 
+    fn start_license_search(&mut self) {
+        self.license_before_search = self.selected_license();
+        self.license_query.clear();
+        self.license_searching = true;
+    }
+
+    /// Leave search mode, restoring the full list with `selection` highlighted
+    fn finish_license_search(&mut self, selection: Option<usize>) {
+        self.license_searching = false;
+        self.license_query.clear();
+        self.license_state.select(selection.or(self.license_before_search));
+        self.license_before_search = None;
+    }
+
+    /// Indices into `LICENSE_TYPES` matching the current search query
+    fn filtered_licenses(&self) -> Vec<usize> {
+        let needle = self.license_query.to_lowercase();
+        LICENSE_TYPES
+            .iter()
+            .enumerate()
+            .filter(|(_, (code, desc))| {
+                desc.to_lowercase().contains(&needle) || code.contains(&needle)
+            })
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Index into `LICENSE_TYPES` of the highlighted list entry
+    fn selected_license(&self) -> Option<usize> {
+        let selected = self.license_state.selected()?;
+        self.filtered_licenses().get(selected).copied()
+    }
+
+    fn reset_license_selection(&mut self) {
+        let selection = if self.filtered_licenses().is_empty() {
+            None
+        } else {
+            Some(0)
+        };
+        self.license_state.select(selection);
+    }
+
     fn next_license(&mut self) {
+        let len = self.filtered_licenses().len();
+        if len == 0 {
+            return;
+        }
         let i = match self.license_state.selected() {
             Some(i) => {
-                if i >= LICENSE_TYPES.len() - 1 {
+                if i >= len - 1 {
                     0
                 } else {
                     i + 1
@@ -163,10 +663,14 @@ impl TuiApp {
     }
 
     fn prev_license(&mut self) {
+        let len = self.filtered_licenses().len();
+        if len == 0 {
+            return;
+        }
         let i = match self.license_state.selected() {
             Some(i) => {
                 if i == 0 {
-                    LICENSE_TYPES.len() - 1
+                    len - 1
                 } else {
                     i - 1
                 }
@@ -176,73 +680,176 @@ impl TuiApp {
         self.license_state.select(Some(i));
     }
 
+    /// Queue every present key, labeled, for copying to the clipboard
+    fn copy_all(&mut self) {
+        if self.generated_spk.is_empty() && self.generated_lkp.is_empty() {
+            self.status_message = "Error: Nothing to copy yet".to_string();
+            return;
+        }
+
+        let description = if self.generated_lkp.is_empty() {
+            ""
+        } else {
+            &self.generated_lkp_description
+        };
+        let block = crate::ui::format_key_block(&self.generated_spk, &self.generated_lkp, description);
+        self.pending_copy = Some(("Keys", block));
+    }
+
+    /// Queue the focused output panel's key, or else the last generated one, for copying
+    fn copy_key(&mut self) {
+        let kind = match self.focused {
+            FocusedWidget::SpkOutput => Some(KeyKind::Spk),
+            FocusedWidget::LkpOutput => Some(KeyKind::Lkp),
+            _ => self.last_generated,
+        };
+        let key = match kind {
+            Some(KeyKind::Spk) => &self.generated_spk,
+            Some(KeyKind::Lkp) => &self.generated_lkp,
+            None => "",
+        };
+        match kind {
+            Some(kind) if !key.is_empty() => self.pending_copy = Some((kind.name(), key.to_string())),
+            _ => self.status_message = "Error: Nothing to copy yet".to_string(),
+        }
+    }
+
+    /// Copy `pending_copy`, through the system clipboard if available, else by OSC 52 to `out`
+    fn flush_copy(&mut self, out: &mut impl io::Write) {
+        let Some((label, text)) = self.pending_copy.take() else {
+            return;
+        };
+
+        #[cfg(feature = "clipboard")]
+        if let Some(clipboard) = &mut self.clipboard {
+            if clipboard.set_text(text.as_str()).is_ok() {
+                self.status_message = format!("{} copied to clipboard", label);
+                return;
+            }
+        }
+
+        // OSC 52 asks the terminal emulator to set the clipboard, but it never says whether it did
+        self.status_message = match execute!(out, CopyToClipboard::to_clipboard_from(text)) {
+            Ok(()) => format!("{} sent to terminal clipboard", label),
+            Err(e) => format!("Error: Clipboard unavailable: {}", e),
+        };
+    }
+
     fn handle_enter(&mut self) {
         match self.focused {
             FocusedWidget::GenerateSpk => self.generate_spk(),
             FocusedWidget::ValidateSpk => self.validate_spk(),
+            FocusedWidget::ValidateLkp => self.validate_lkp(),
             FocusedWidget::GenerateLkp => self.generate_lkp(),
             _ => {}
         }
     }
 
-    fn generate_spk(&mut self) {
-        if self.pid.trim().is_empty() {
-            self.status_message = "Error: PID is required".to_string();
+    /// Run `work` on a new thread; `poll_job` picks up the result
+    fn spawn_job(&mut self, work: impl FnOnce() -> JobResult + Send + 'static) {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(work());
+        });
+        self.job = Some(receiver);
+    }
+
+    /// Apply the result of a finished worker, if there is one
+    fn poll_job(&mut self) {
+        let Some(receiver) = &self.job else {
             return;
-        }
+        };
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => {
+                self.spinner_frame = (self.spinner_frame + 1) % SPINNER.len();
+                return;
+            }
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.job = None;
+                self.status_message = "Error: Generation stopped unexpectedly".to_string();
+                return;
+            }
+        };
+        self.job = None;
 
-        match generate_spk(&self.pid) {
-            Ok(spk) => {
+        match result {
+            JobResult::Spk(Ok(spk)) => {
                 self.generated_spk = spk;
+                self.last_generated = Some(KeyKind::Spk);
+                self.spk_scroll.offset = 0;
                 self.status_message = "SPK generated successfully!".to_string();
             }
-            Err(e) => {
+            JobResult::Lkp(Ok(lkp), license_info) => {
+                self.generated_lkp = lkp;
+                self.last_generated = Some(KeyKind::Lkp);
+                self.lkp_scroll.offset = 0;
+                self.status_message = format!(
+                    "LKP generated successfully! ({})",
+                    license_info.description
+                );
+                self.generated_lkp_description = license_info.description;
+            }
+            JobResult::Spk(Err(e)) | JobResult::Lkp(Err(e), _) => {
                 self.status_message = format!("Error: {}", e);
             }
         }
     }
 
+    fn generate_spk(&mut self) {
+        if self.job.is_some() {
+            return;
+        }
+        if self.pid.value.trim().is_empty() {
+            self.status_message = "Error: PID is required".to_string();
+            return;
+        }
+
+        let pid = normalize_pid(&self.pid.value);
+        self.status_message = "Generating SPK...".to_string();
+        self.spawn_job(move || JobResult::Spk(generate_spk(&pid)));
+    }
+
     fn validate_spk(&mut self) {
-        if self.pid.trim().is_empty() {
+        if self.pid.value.trim().is_empty() {
             self.status_message = "Error: PID is required".to_string();
             return;
         }
 
-        if self.spk.trim().is_empty() {
+        if self.spk.value.trim().is_empty() {
             self.status_message = "Error: SPK is required for validation".to_string();
             return;
         }
 
-        match validate_tskey(
-            &self.pid,
-            &self.spk,
-            SPKCurve::gx(),
-            SPKCurve::gy(),
-            SPKCurve::kx(),
-            SPKCurve::ky(),
-            BigUint::from(SPKCurve::A),
-            SPKCurve::p(),
-            true,
-        ) {
-            Ok(true) => {
-                self.status_message = "SPK validation successful!".to_string();
-            }
-            Ok(false) => {
-                self.status_message = "Error: SPK does not match the PID".to_string();
-            }
-            Err(e) => {
-                self.status_message = format!("Error: {}", e);
-            }
+        let diagnosis = diagnose_tskey(&normalize_pid(&self.pid.value), &self.spk.value, &SPKCurve::params(), KeyKind::Spk);
+        self.status_message = validation_status(diagnosis, "SPK validation successful!", "Error: SPK does not match the PID");
+    }
+
+    fn validate_lkp(&mut self) {
+        if self.pid.value.trim().is_empty() {
+            self.status_message = "Error: PID is required".to_string();
+            return;
         }
+
+        if self.lkp.value.trim().is_empty() {
+            self.status_message = "Error: LKP is required for validation".to_string();
+            return;
+        }
+
+        let diagnosis = diagnose_tskey(&normalize_pid(&self.pid.value), &self.lkp.value, &LKPCurve::params(), KeyKind::Lkp);
+        self.status_message = validation_status(diagnosis, "LKP validation successful!", "Error: LKP does not match the PID");
     }
 
     fn generate_lkp(&mut self) {
-        if self.pid.trim().is_empty() {
+        if self.job.is_some() {
+            return;
+        }
+        if self.pid.value.trim().is_empty() {
             self.status_message = "Error: PID is required".to_string();
             return;
         }
 
-        let count: u32 = match self.count.parse() {
+        let count: u32 = match self.count.value.parse() {
             Ok(c) if (1..=9999).contains(&c) => c,
             _ => {
                 self.status_message = "Error: Count must be between 1 and 9999".to_string();
@@ -250,8 +857,13 @@ impl TuiApp {
             }
         };
 
-        let selected = self.license_state.selected().unwrap_or(0);
-        let license_type = LICENSE_TYPES[selected].0;
+        let license_type = match self.selected_license() {
+            Some(selected) => LICENSE_TYPES[selected].0,
+            None => {
+                self.status_message = "Error: No license type selected".to_string();
+                return;
+            }
+        };
         
         let license_info = match LicenseInfo::parse(license_type) {
             Ok(info) => info,
@@ -261,28 +873,23 @@ impl TuiApp {
             }
         };
 
-        match generate_lkp(
-            &self.pid,
-            count,
-            license_info.chid,
-            license_info.major_ver,
-            license_info.minor_ver,
-        ) {
-            Ok(lkp) => {
-                self.generated_lkp = lkp;
-                self.status_message = format!(
-                    "LKP generated successfully! ({})",
-                    license_info.description
-                );
-            }
-            Err(e) => {
-                self.status_message = format!("Error: {}", e);
-            }
-        }
+        let pid = normalize_pid(&self.pid.value);
+        self.status_message = "Generating LKP...".to_string();
+        self.spawn_job(move || {
+            let lkp = generate_lkp(
+                &pid,
+                count,
+                license_info.chid,
+                license_info.major_ver,
+                license_info.minor_ver,
+            );
+            JobResult::Lkp(lkp, license_info)
+        });
     }
 }
 
 fn ui(f: &mut Frame, app: &mut TuiApp) {
+    let theme = app.theme;
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -291,11 +898,11 @@ fn ui(f: &mut Frame, app: &mut TuiApp) {
             Constraint::Length(3),  // Status
             Constraint::Length(2),  // Help
         ])
-        .split(f.size());
+        .split(f.area());
 
     // Title
     let title = Paragraph::new("LyssaRDSGen - RDS License Key Generator")
-        .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+        .style(Style::default().fg(theme.title).add_modifier(Modifier::BOLD))
         .alignment(Alignment::Center)
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(title, chunks[0]);
@@ -312,72 +919,128 @@ fn ui(f: &mut Frame, app: &mut TuiApp) {
         .constraints([
             Constraint::Length(3),  // PID
             Constraint::Length(3),  // SPK
+            Constraint::Length(3),  // LKP
             Constraint::Length(3),  // Count
             Constraint::Min(5),     // License
             Constraint::Length(3),  // Buttons
         ])
         .split(main_chunks[0]);
 
+    app.click_areas.pid = left_chunks[0];
+    app.click_areas.spk = left_chunks[1];
+    app.click_areas.lkp = left_chunks[2];
+    app.click_areas.count = left_chunks[3];
+    app.click_areas.license = left_chunks[4];
+
     // PID input
-    let pid_style = if matches!(app.focused, FocusedWidget::Input(InputField::Pid)) {
-        Style::default().fg(Color::Yellow)
+    // PID border reflects validity once something has been entered, and the title
+    // warns early when no SPKID can be extracted (generation would fail)
+    let pid_focused = matches!(app.focused, FocusedWidget::Input(InputField::Pid));
+    let mut pid_title = "Product ID".to_string();
+    let pid_style = if app.pid.value.trim().is_empty() {
+        if pid_focused {
+            Style::default().fg(theme.focus)
+        } else {
+            Style::default()
+        }
     } else {
-        Style::default()
+        let pid = normalize_pid(&app.pid.value);
+        let color = match get_spkid(&pid) {
+            Err(e) => {
+                pid_title = format!("Product ID ⚠ {}", e);
+                theme.warning
+            }
+            Ok(spkid) if validate_pid(&pid).is_ok() => {
+                pid_title = format!("Product ID (SPKID {})", spkid);
+                theme.valid
+            }
+            Ok(_) => theme.error,
+        };
+        if pid_focused {
+            Style::default().fg(color).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(color)
+        }
     };
-    let pid_input = Paragraph::new(app.pid.as_str())
-        .block(Block::default().borders(Borders::ALL).title("Product ID").border_style(pid_style));
+    let pid_input = Paragraph::new(app.pid.render(pid_focused))
+        .block(Block::default().borders(Borders::ALL).title(pid_title).border_style(pid_style));
     f.render_widget(pid_input, left_chunks[0]);
 
     // SPK input
-    let spk_style = if matches!(app.focused, FocusedWidget::Input(InputField::Spk)) {
-        Style::default().fg(Color::Yellow)
+    let spk_focused = matches!(app.focused, FocusedWidget::Input(InputField::Spk));
+    let spk_style = if spk_focused {
+        Style::default().fg(theme.focus)
     } else {
         Style::default()
     };
-    let spk_input = Paragraph::new(app.spk.as_str())
+    let spk_input = Paragraph::new(app.spk.render(spk_focused))
         .block(Block::default().borders(Borders::ALL).title("Existing SPK (Optional)").border_style(spk_style));
     f.render_widget(spk_input, left_chunks[1]);
 
+    // LKP input
+    let lkp_focused = matches!(app.focused, FocusedWidget::Input(InputField::Lkp));
+    let lkp_style = if lkp_focused {
+        Style::default().fg(theme.focus)
+    } else {
+        Style::default()
+    };
+    let lkp_input = Paragraph::new(app.lkp.render(lkp_focused))
+        .block(Block::default().borders(Borders::ALL).title("Existing LKP (Optional)").border_style(lkp_style));
+    f.render_widget(lkp_input, left_chunks[2]);
+
     // Count input
-    let count_style = if matches!(app.focused, FocusedWidget::Input(InputField::Count)) {
-        Style::default().fg(Color::Yellow)
+    let count_focused = matches!(app.focused, FocusedWidget::Input(InputField::Count));
+    let count_style = if count_focused {
+        Style::default().fg(theme.focus)
     } else {
         Style::default()
     };
-    let count_input = Paragraph::new(app.count.as_str())
+    let count_input = Paragraph::new(app.count.render(count_focused))
         .block(Block::default().borders(Borders::ALL).title("License Count (1-9999)").border_style(count_style));
-    f.render_widget(count_input, left_chunks[2]);
+    f.render_widget(count_input, left_chunks[3]);
 
     // License type list
     let license_style = if matches!(app.focused, FocusedWidget::Input(InputField::License)) {
-        Style::default().fg(Color::Yellow)
+        Style::default().fg(theme.focus)
     } else {
         Style::default()
     };
-    let licenses: Vec<ListItem> = LICENSE_TYPES
-        .iter()
-        .map(|(_, desc)| ListItem::new(*desc))
+    let licenses: Vec<ListItem> = app
+        .filtered_licenses()
+        .into_iter()
+        .map(|idx| ListItem::new(LICENSE_TYPES[idx].1))
         .collect();
+    let license_title = if app.license_searching {
+        format!("License Type - search: /{}_ (Enter: pick, Esc: cancel)", app.license_query)
+    } else {
+        "License Type (↑↓ to select, / to search)".to_string()
+    };
     let licenses_list = List::new(licenses)
-        .block(Block::default().borders(Borders::ALL).title("License Type (↑↓ to select)").border_style(license_style))
-        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
+        .block(Block::default().borders(Borders::ALL).title(license_title).border_style(license_style))
+        .highlight_style(Style::default().bg(theme.highlight).add_modifier(Modifier::BOLD))
         .highlight_symbol(">> ");
-    f.render_stateful_widget(licenses_list, left_chunks[3], &mut app.license_state);
+    f.render_stateful_widget(licenses_list, left_chunks[4], &mut app.license_state);
 
     // Buttons
     let button_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
-            Constraint::Percentage(33),
-            Constraint::Percentage(33),
-            Constraint::Percentage(34),
+            Constraint::Percentage(25),
+            Constraint::Percentage(25),
+            Constraint::Percentage(25),
+            Constraint::Percentage(25),
         ])
-        .split(left_chunks[4]);
+        .split(left_chunks[5]);
+
+    app.click_areas.generate_spk = button_chunks[0];
+    app.click_areas.validate_spk = button_chunks[1];
+    app.click_areas.validate_lkp = button_chunks[2];
+    app.click_areas.generate_lkp = button_chunks[3];
 
     let gen_spk_style = if matches!(app.focused, FocusedWidget::GenerateSpk) {
-        Style::default().fg(Color::Black).bg(Color::Green)
+        Style::default().fg(theme.button_text).bg(theme.generate_spk)
     } else {
-        Style::default().fg(Color::Green)
+        Style::default().fg(theme.generate_spk)
     };
     let gen_spk_btn = Paragraph::new("Generate SPK")
         .alignment(Alignment::Center)
@@ -385,24 +1048,34 @@ fn ui(f: &mut Frame, app: &mut TuiApp) {
     f.render_widget(gen_spk_btn, button_chunks[0]);
 
     let val_spk_style = if matches!(app.focused, FocusedWidget::ValidateSpk) {
-        Style::default().fg(Color::Black).bg(Color::Blue)
+        Style::default().fg(theme.button_text).bg(theme.validate)
     } else {
-        Style::default().fg(Color::Blue)
+        Style::default().fg(theme.validate)
     };
     let val_spk_btn = Paragraph::new("Validate SPK")
         .alignment(Alignment::Center)
         .block(Block::default().borders(Borders::ALL).border_style(val_spk_style));
     f.render_widget(val_spk_btn, button_chunks[1]);
 
+    let val_lkp_style = if matches!(app.focused, FocusedWidget::ValidateLkp) {
+        Style::default().fg(theme.button_text).bg(theme.validate)
+    } else {
+        Style::default().fg(theme.validate)
+    };
+    let val_lkp_btn = Paragraph::new("Validate LKP")
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL).border_style(val_lkp_style));
+    f.render_widget(val_lkp_btn, button_chunks[2]);
+
     let gen_lkp_style = if matches!(app.focused, FocusedWidget::GenerateLkp) {
-        Style::default().fg(Color::Black).bg(Color::Cyan)
+        Style::default().fg(theme.button_text).bg(theme.generate_lkp)
     } else {
-        Style::default().fg(Color::Cyan)
+        Style::default().fg(theme.generate_lkp)
     };
     let gen_lkp_btn = Paragraph::new("Generate LKP")
         .alignment(Alignment::Center)
         .block(Block::default().borders(Borders::ALL).border_style(gen_lkp_style));
-    f.render_widget(gen_lkp_btn, button_chunks[2]);
+    f.render_widget(gen_lkp_btn, button_chunks[3]);
 
     // Right panel - Output
     let right_chunks = Layout::default()
@@ -410,59 +1083,200 @@ fn ui(f: &mut Frame, app: &mut TuiApp) {
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
         .split(main_chunks[1]);
 
+    app.click_areas.spk_output = right_chunks[0];
+    app.click_areas.lkp_output = right_chunks[1];
+
     // SPK output
-    let spk_output = Paragraph::new(app.generated_spk.as_str())
-        .style(Style::default().fg(Color::Green))
-        .block(Block::default().borders(Borders::ALL).title("Generated SPK"))
-        .wrap(Wrap { trim: false });
-    f.render_widget(spk_output, right_chunks[0]);
+    app.spk_scroll.fit(&app.generated_spk, right_chunks[0]);
+    render_output(
+        f,
+        &theme,
+        "Generated SPK",
+        &app.generated_spk,
+        &app.spk_scroll,
+        matches!(app.focused, FocusedWidget::SpkOutput),
+        right_chunks[0],
+    );
 
     // LKP output
-    let lkp_output = Paragraph::new(app.generated_lkp.as_str())
-        .style(Style::default().fg(Color::Green))
-        .block(Block::default().borders(Borders::ALL).title("Generated LKP"))
-        .wrap(Wrap { trim: false });
-    f.render_widget(lkp_output, right_chunks[1]);
+    app.lkp_scroll.fit(&app.generated_lkp, right_chunks[1]);
+    render_output(
+        f,
+        &theme,
+        "Generated LKP",
+        &app.generated_lkp,
+        &app.lkp_scroll,
+        matches!(app.focused, FocusedWidget::LkpOutput),
+        right_chunks[1],
+    );
 
     // Status bar
     let status_color = if app.status_message.starts_with("Error") {
-        Color::Red
+        theme.error
+    } else {
+        theme.valid
+    };
+    let status_text = if app.job.is_some() {
+        format!("{} {}", SPINNER[app.spinner_frame], app.status_message)
     } else {
-        Color::Green
+        app.status_message.clone()
     };
-    let status = Paragraph::new(app.status_message.as_str())
+    let status = Paragraph::new(status_text)
         .style(Style::default().fg(status_color))
         .block(Block::default().borders(Borders::ALL).title("Status"));
     f.render_widget(status, chunks[2]);
 
     // Help bar
-    let help_text = "Tab: Next field | Shift+Tab: Prev | Enter: Execute | ↑↓: Select license | Esc/q: Quit";
+    let help_text = "Tab: Next field | Shift+Tab: Prev | Enter/Click: Execute | ←→/Home/End: Move cursor | ↑↓: Select license | /: Search licenses | PgUp/PgDn/Wheel: Scroll output | F2: Copy all | c: Copy key | F3: Theme | ?: Help | Esc/q: Quit";
     let help = Paragraph::new(help_text)
-        .style(Style::default().fg(Color::Gray))
+        .style(Style::default().fg(theme.help))
         .alignment(Alignment::Center);
     f.render_widget(help, chunks[3]);
+
+    if app.show_help {
+        render_help(f, &theme);
+    }
 }
 
-pub fn run_tui() -> Result<(), Box<dyn std::error::Error>> {
+/// Keybindings and workflows, as (key, description) rows under section headings
+const HELP_SECTIONS: &[(&str, &[(&str, &str)])] = &[
+    ("Navigation", &[
+        ("Tab / Shift+Tab", "Next / previous field or button"),
+        ("Click", "Focus a field, or press a button"),
+        ("←→ Home End", "Move the cursor in a text field"),
+        ("Esc / q", "Quit (or close this help)"),
+    ]),
+    ("Actions", &[
+        ("Enter", "Press the focused button"),
+        ("F2", "Copy all generated keys to the clipboard"),
+        ("c", "Copy the focused output's key, or else the last generated one"),
+        ("F3", "Cycle the color theme (default, mono, high-contrast)"),
+        ("?", "Toggle this help"),
+    ]),
+    ("License type", &[
+        ("↑↓", "Select a license"),
+        ("/", "Search by description or code; Enter picks, Esc cancels"),
+    ]),
+    ("Output", &[
+        ("↑↓ j k PgUp PgDn", "Scroll the focused output panel"),
+        ("Mouse wheel", "Scroll the panel under the pointer"),
+    ]),
+    ("Workflows", &[
+        ("New server", "Enter the PID, Generate SPK, pick a license and count, Generate LKP"),
+        ("Existing SPK", "Enter the PID and SPK, Validate SPK, then Generate LKP"),
+        ("Existing LKP", "Enter the PID and LKP, then Validate LKP"),
+    ]),
+];
+
+/// Centered help popup drawn over the rest of the UI
+fn render_help(f: &mut Frame, theme: &Theme) {
+    let mut lines = Vec::new();
+    for (i, (section, rows)) in HELP_SECTIONS.iter().enumerate() {
+        if i > 0 {
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from(Span::styled(
+            *section,
+            Style::default().fg(theme.title).add_modifier(Modifier::BOLD),
+        )));
+        for (key, description) in rows.iter() {
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {:18}", key), Style::default().fg(theme.focus)),
+                Span::raw(*description),
+            ]));
+        }
+    }
+
+    let area = centered_rect(92, lines.len() as u16 + 2, f.area());
+    let popup = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title("Help (Esc to close)"));
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+/// `width` x `height` rectangle centered in `area`, shrunk to fit
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Wrapped, scrollable key output with a scrollbar when the key does not fit
+fn render_output(
+    f: &mut Frame,
+    theme: &Theme,
+    title: &str,
+    text: &str,
+    scroll: &OutputScroll,
+    focused: bool,
+    area: Rect,
+) {
+    let border_style = if focused {
+        Style::default().fg(theme.focus)
+    } else {
+        Style::default()
+    };
+    let title = if scroll.max > 0 {
+        format!("{} [{}/{}]", title, scroll.offset + 1, scroll.max + 1)
+    } else {
+        title.to_string()
+    };
+
+    let output = Paragraph::new(text)
+        .style(Style::default().fg(theme.output))
+        .block(Block::default().borders(Borders::ALL).title(title).border_style(border_style))
+        .wrap(Wrap { trim: false })
+        .scroll((scroll.offset, 0));
+    f.render_widget(output, area);
+
+    if scroll.max > 0 {
+        let mut state = ScrollbarState::new(scroll.max as usize + 1).position(scroll.offset as usize);
+        f.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight),
+            area,
+            &mut state,
+        );
+    }
+}
+
+pub fn run_tui(theme: TuiTheme) -> Result<(), Box<dyn std::error::Error>> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // Create app
-    let mut app = TuiApp::new();
+    let mut app = TuiApp::new(theme);
 
     // Main loop
     loop {
+        app.poll_job();
         terminal.draw(|f| ui(f, &mut app))?;
 
         if event::poll(std::time::Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
                     app.handle_key(key.code);
+                    app.flush_copy(terminal.backend_mut());
+                }
+                Event::Paste(text) => {
+                    app.handle_paste(&text);
                 }
+                Event::Mouse(mouse) => match mouse.kind {
+                    MouseEventKind::Down(MouseButton::Left) => app.handle_click(mouse.column, mouse.row),
+                    MouseEventKind::ScrollDown => app.handle_wheel(mouse.column, mouse.row, true),
+                    MouseEventKind::ScrollUp => app.handle_wheel(mouse.column, mouse.row, false),
+                    _ => {}
+                },
+                _ => {}
             }
         }
 
@@ -476,9 +1290,155 @@ pub fn run_tui() -> Result<(), Box<dyn std::error::Error>> {
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(line: &Line<'_>) -> Vec<String> {
+        line.spans.iter().map(|span| span.content.to_string()).collect()
+    }
+
+    #[test]
+    fn test_text_input_edits_multibyte_chars() {
+        let mut input = TextInput::new("aé日");
+        assert_eq!(input.cursor, 3);
+
+        input.move_left();
+        input.insert('ü');
+        assert_eq!((input.value.as_str(), input.cursor), ("aéü日", 3));
+
+        input.backspace();
+        input.backspace();
+        assert_eq!((input.value.as_str(), input.cursor), ("a日", 1));
+
+        input.delete();
+        assert_eq!((input.value.as_str(), input.cursor), ("a", 1));
+        input.delete();
+        assert_eq!(input.value, "a");
+
+        input.move_home();
+        input.backspace();
+        assert_eq!((input.value.as_str(), input.cursor), ("a", 0));
+    }
+
+    #[test]
+    fn test_text_input_renders_cursor() {
+        let mut input = TextInput::new("é日");
+        assert_eq!(spans(&input.render(true)), ["é日", " ", ""]);
+        assert_eq!(spans(&input.render(false)), ["é日"]);
+
+        input.move_left();
+        assert_eq!(spans(&input.render(true)), ["é", "日", ""]);
+        input.move_home();
+        assert_eq!(spans(&input.render(true)), ["", "é", "日"]);
+    }
+
+    #[test]
+    fn test_output_scroll_without_overflow() {
+        let mut scroll = OutputScroll::default();
+        scroll.fit("", Rect::new(0, 0, 20, 10));
+        assert_eq!(scroll.max, 0);
+        scroll.scroll_down(5);
+        assert_eq!(scroll.offset, 0);
+
+        scroll.fit("short\nlines", Rect::new(0, 0, 20, 10));
+        scroll.scroll_down(1);
+        assert_eq!((scroll.offset, scroll.max), (0, 0));
+    }
+
+    #[test]
+    fn test_output_scroll_zero_height_panel() {
+        let mut scroll = OutputScroll::default();
+        scroll.fit("a\nb\nc", Rect::new(0, 0, 20, 0));
+        assert_eq!(scroll.max, 3);
+        scroll.scroll_down(10);
+        assert_eq!(scroll.offset, 3);
+        scroll.scroll_up(10);
+        assert_eq!(scroll.offset, 0);
+    }
+
+    #[test]
+    fn test_output_scroll_clamps_after_resize() {
+        let text = "line\n".repeat(9) + &"x".repeat(15);
+        let mut scroll = OutputScroll::default();
+        // 10 columns inside the borders wrap the last line onto two rows
+        scroll.fit(&text, Rect::new(0, 0, 12, 5));
+        assert_eq!(scroll.max, 8);
+        scroll.scroll_down(u16::MAX);
+        assert_eq!(scroll.offset, 8);
+
+        scroll.fit(&text, Rect::new(0, 0, 12, 10));
+        assert_eq!((scroll.offset, scroll.max), (3, 3));
+        scroll.fit(&text, Rect::new(0, 0, 40, 20));
+        assert_eq!((scroll.offset, scroll.max), (0, 0));
+    }
+
+    fn type_query(app: &mut TuiApp, query: &str) {
+        for c in query.chars() {
+            app.handle_search_key(KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn test_license_search_selects_filtered_entry() {
+        let mut app = TuiApp::new(TuiTheme::Default);
+        app.start_license_search();
+        assert!(app.license_searching);
+        assert_eq!(app.license_before_search, Some(18));
+        assert_eq!(app.selected_license(), Some(18));
+
+        type_query(&mut app, "2025");
+        assert_eq!(app.filtered_licenses(), [21, 22, 23]);
+        assert_eq!(app.license_state.selected(), Some(0));
+        assert_eq!(app.selected_license(), Some(21));
+
+        app.handle_search_key(KeyCode::Down);
+        assert_eq!(app.selected_license(), Some(22));
+
+        app.handle_search_key(KeyCode::Enter);
+        assert!(!app.license_searching);
+        assert!(app.license_query.is_empty());
+        assert_eq!(app.license_before_search, None);
+        assert_eq!(app.license_state.selected(), Some(22));
+        assert_eq!(app.selected_license(), Some(22));
+    }
+
+    #[test]
+    fn test_license_search_esc_restores_selection() {
+        let mut app = TuiApp::new(TuiTheme::Default);
+        app.start_license_search();
+        type_query(&mut app, "per user");
+        app.handle_search_key(KeyCode::Down);
+        assert_eq!(app.selected_license(), Some(5));
+
+        app.handle_search_key(KeyCode::Esc);
+        assert!(!app.license_searching);
+        assert_eq!(app.selected_license(), Some(18));
+    }
+
+    #[test]
+    fn test_license_search_without_matches() {
+        let mut app = TuiApp::new(TuiTheme::Default);
+        app.start_license_search();
+        type_query(&mut app, "xyz");
+        assert!(app.filtered_licenses().is_empty());
+        assert_eq!(app.license_state.selected(), None);
+        assert_eq!(app.selected_license(), None);
+
+        app.handle_search_key(KeyCode::Down);
+        assert_eq!(app.selected_license(), None);
+
+        // Confirming an empty result keeps the previous selection
+        app.finish_license_search(app.selected_license());
+        assert!(!app.license_searching);
+        assert_eq!(app.selected_license(), Some(18));
+    }
+}
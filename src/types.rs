@@ -1,9 +1,12 @@
 //! Common types and constants
 
+use crate::crypto::EllipticCurvePoint;
 use num_bigint::BigUint;
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::OnceLock;
 
-/// Character set for key encoding (base-24)
-pub const KCHARS: &str = "BCDFGHJKMPQRTVWXY2346789";
+pub use crate::crypto::encoding::KCHARS;
 
 /// License types with descriptions
 pub const LICENSE_TYPES: &[(&str, &str)] = &[
@@ -33,6 +36,232 @@ pub const LICENSE_TYPES: &[(&str, &str)] = &[
     ("034_10_3", "Windows Server 2025 VDI Suite"),
 ];
 
+/// Find a license type by its code or by a case-insensitive substring of its
+/// description, e.g. `2022 Per Device` for `029_10_2`
+///
+/// Fails if nothing matches or if the description matches several types.
+pub fn find_license(query: &str) -> anyhow::Result<&'static (&'static str, &'static str)> {
+    let query = query.trim();
+    if let Some(license) = LICENSE_TYPES.iter().find(|(code, _)| *code == query) {
+        return Ok(license);
+    }
+    
+    let needle = query.to_lowercase();
+    let matches: Vec<&(&str, &str)> = LICENSE_TYPES
+        .iter()
+        .filter(|(_, desc)| desc.to_lowercase().contains(&needle))
+        .collect();
+    match matches[..] {
+        [license] => Ok(license),
+        [] => anyhow::bail!("Unknown license type: {} (see --list)", query),
+        _ => {
+            let candidates: Vec<String> = matches
+                .iter()
+                .map(|(code, desc)| format!("{} ({})", code, desc))
+                .collect();
+            anyhow::bail!("License type '{}' is ambiguous: {}", query, candidates.join(", "))
+        }
+    }
+}
+
+/// `CHID_MAJOR_MINOR` code of a license, e.g. `029_10_2`
+pub fn license_code(chid: u32, major_ver: u32, minor_ver: u32) -> String {
+    format!("{:03}_{}_{}", chid, major_ver, minor_ver)
+}
+
+/// Description of the license with this CHID and version, if it is in `LICENSE_TYPES`
+pub fn describe_license(chid: u32, major_ver: u32, minor_ver: u32) -> Option<&'static str> {
+    let code = license_code(chid, major_ver, minor_ver);
+    LICENSE_TYPES
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, desc)| *desc)
+}
+
+/// The two kinds of key, each signed on its own curve
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyKind {
+    /// License Server ID
+    Spk,
+    /// License Key Pack
+    Lkp,
+}
+
+impl KeyKind {
+    /// `Spk` for `true`, as taken by the `is_spk` parameters of the C and wasm bindings
+    pub fn from_is_spk(is_spk: bool) -> Self {
+        if is_spk {
+            Self::Spk
+        } else {
+            Self::Lkp
+        }
+    }
+
+    /// `"SPK"` or `"LKP"`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Spk => "SPK",
+            Self::Lkp => "LKP",
+        }
+    }
+}
+
+impl std::str::FromStr for KeyKind {
+    type Err = anyhow::Error;
+
+    /// `spk` or `lkp`, in any case
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "spk" => Ok(Self::Spk),
+            "lkp" => Ok(Self::Lkp),
+            other => anyhow::bail!("Unknown key kind '{}' (expected spk or lkp)", other),
+        }
+    }
+}
+
+/// Complete parameter set of a signing curve `y^2 = x^3 + ax + b (mod p)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurveParams {
+    pub p: BigUint,
+    /// Order of the generator point
+    pub n: BigUint,
+    pub a: BigUint,
+    pub b: BigUint,
+    /// Generator point
+    pub gx: BigUint,
+    pub gy: BigUint,
+    /// Public key point
+    pub kx: BigUint,
+    pub ky: BigUint,
+    pub priv_key: BigUint,
+}
+
+/// Curve parameters as written in a TOML file (decimal strings)
+#[derive(Deserialize)]
+struct CurveParamsToml {
+    p: String,
+    n: String,
+    a: String,
+    b: String,
+    gx: String,
+    gy: String,
+    kx: String,
+    ky: String,
+    priv_key: String,
+}
+
+impl CurveParams {
+    /// Built-in curve for `kind`
+    pub fn for_kind(kind: KeyKind) -> Self {
+        match kind {
+            KeyKind::Spk => SPKCurve::params(),
+            KeyKind::Lkp => LKPCurve::params(),
+        }
+    }
+
+    /// Generator point `G`
+    pub fn generator(&self) -> EllipticCurvePoint {
+        EllipticCurvePoint::new(
+            self.gx.clone(),
+            self.gy.clone(),
+            self.a.clone(),
+            self.b.clone(),
+            self.p.clone(),
+        )
+    }
+
+    /// Public key point `K = G * priv_key`
+    pub fn public_key(&self) -> EllipticCurvePoint {
+        EllipticCurvePoint::new(
+            self.kx.clone(),
+            self.ky.clone(),
+            self.a.clone(),
+            self.b.clone(),
+            self.p.clone(),
+        )
+    }
+
+    /// Ensure the generator and public key points lie on the curve
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !self.generator().is_on_curve() {
+            anyhow::bail!("Generator point (gx, gy) is not on the curve");
+        }
+        if !self.public_key().is_on_curve() {
+            anyhow::bail!("Public key point (kx, ky) is not on the curve");
+        }
+        Ok(())
+    }
+
+    fn from_toml(raw: CurveParamsToml) -> anyhow::Result<Self> {
+        fn parse(name: &str, value: &str) -> anyhow::Result<BigUint> {
+            BigUint::parse_bytes(value.trim().as_bytes(), 10)
+                .ok_or_else(|| anyhow::anyhow!("Curve parameter `{}` is not a decimal integer", name))
+        }
+
+        let params = Self {
+            p: parse("p", &raw.p)?,
+            n: parse("n", &raw.n)?,
+            a: parse("a", &raw.a)?,
+            b: parse("b", &raw.b)?,
+            gx: parse("gx", &raw.gx)?,
+            gy: parse("gy", &raw.gy)?,
+            kx: parse("kx", &raw.kx)?,
+            ky: parse("ky", &raw.ky)?,
+            priv_key: parse("priv_key", &raw.priv_key)?,
+        };
+        params.validate()?;
+        Ok(params)
+    }
+}
+
+/// Custom curve overrides loaded from a TOML file with optional `[spk]` and `[lkp]` tables
+///
+/// Each table lists `p`, `n`, `a`, `b`, `gx`, `gy`, `kx`, `ky` and `priv_key` as decimal strings.
+pub struct CurveFile {
+    pub spk: Option<CurveParams>,
+    pub lkp: Option<CurveParams>,
+}
+
+impl CurveFile {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Raw {
+            spk: Option<CurveParamsToml>,
+            lkp: Option<CurveParamsToml>,
+        }
+
+        let raw: Raw = toml::from_str(contents)?;
+        let spk = raw
+            .spk
+            .map(CurveParams::from_toml)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("[spk] curve: {}", e))?;
+        let lkp = raw
+            .lkp
+            .map(CurveParams::from_toml)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("[lkp] curve: {}", e))?;
+
+        if spk.is_none() && lkp.is_none() {
+            anyhow::bail!("Curve file must define an [spk] and/or [lkp] table");
+        }
+        Ok(Self { spk, lkp })
+    }
+}
+
+/// Parse a built-in decimal curve constant
+fn decimal(digits: &str) -> BigUint {
+    BigUint::parse_bytes(digits.as_bytes(), 10).expect("valid built-in curve constant")
+}
+
 /// Elliptic curve parameters for SPK
 #[derive(Clone)]
 pub struct SPKCurve;
@@ -41,47 +270,52 @@ impl SPKCurve {
     pub const A: u32 = 1;
     pub const B: u32 = 0;
     
+    /// All parameters, parsed from decimal on first use only
+    fn cached() -> &'static CurveParams {
+        static PARAMS: OnceLock<CurveParams> = OnceLock::new();
+        PARAMS.get_or_init(|| CurveParams {
+            p: decimal("21782971228112002125810473336838725345308036616026120243639513697227789232461459408261967852943809534324870610618161"),
+            n: decimal("629063109922370885449"),
+            a: BigUint::from(Self::A),
+            b: BigUint::from(Self::B),
+            gx: decimal("10692194187797070010417373067833672857716423048889432566885309624149667762706899929433420143814127803064297378514651"),
+            gy: decimal("14587399915883137990539191966406864676102477026583239850923355829082059124877792299572208431243410905713755917185109"),
+            kx: decimal("3917395608307488535457389605368226854270150445881753750395461980792533894109091921400661704941484971683063487980768"),
+            ky: decimal("8858262671783403684463979458475735219807686373661776500155868309933327116988404547349319879900761946444470688332645"),
+            priv_key: decimal("153862071918555979944"),
+        })
+    }
+    
     pub fn p() -> BigUint {
-        BigUint::parse_bytes(
-            b"21782971228112002125810473336838725345308036616026120243639513697227789232461459408261967852943809534324870610618161",
-            10
-        ).unwrap()
+        Self::cached().p.clone()
     }
     
     pub fn n() -> BigUint {
-        BigUint::parse_bytes(b"629063109922370885449", 10).unwrap()
+        Self::cached().n.clone()
     }
     
     pub fn gx() -> BigUint {
-        BigUint::parse_bytes(
-            b"10692194187797070010417373067833672857716423048889432566885309624149667762706899929433420143814127803064297378514651",
-            10
-        ).unwrap()
+        Self::cached().gx.clone()
     }
     
     pub fn gy() -> BigUint {
-        BigUint::parse_bytes(
-            b"14587399915883137990539191966406864676102477026583239850923355829082059124877792299572208431243410905713755917185109",
-            10
-        ).unwrap()
+        Self::cached().gy.clone()
     }
     
     pub fn kx() -> BigUint {
-        BigUint::parse_bytes(
-            b"3917395608307488535457389605368226854270150445881753750395461980792533894109091921400661704941484971683063487980768",
-            10
-        ).unwrap()
+        Self::cached().kx.clone()
     }
     
     pub fn ky() -> BigUint {
-        BigUint::parse_bytes(
-            b"8858262671783403684463979458475735219807686373661776500155868309933327116988404547349319879900761946444470688332645",
-            10
-        ).unwrap()
+        Self::cached().ky.clone()
     }
     
     pub fn priv_key() -> BigUint {
-        BigUint::parse_bytes(b"153862071918555979944", 10).unwrap()
+        Self::cached().priv_key.clone()
+    }
+    
+    pub fn params() -> CurveParams {
+        Self::cached().clone()
     }
 }
 
@@ -93,52 +327,58 @@ impl LKPCurve {
     pub const A: u32 = 1;
     pub const B: u32 = 0;
     
+    /// All parameters, parsed from decimal on first use only
+    fn cached() -> &'static CurveParams {
+        static PARAMS: OnceLock<CurveParams> = OnceLock::new();
+        PARAMS.get_or_init(|| CurveParams {
+            p: decimal("28688293616765795404141427476803815352899912533728694325464374376776313457785622361119232589082131818578591461837297"),
+            n: decimal("675048016158598417213"),
+            a: BigUint::from(Self::A),
+            b: BigUint::from(Self::B),
+            gx: decimal("18999816458520350299014628291870504329073391058325678653840191278128672378485029664052827205905352913351648904170809"),
+            gy: decimal("7233699725243644729688547165924232430035643592445942846958231777803539836627943189850381859836033366776176689124317"),
+            kx: decimal("7147768390112741602848314103078506234267895391544114241891627778383312460777957307647946308927283757886117119137500"),
+            ky: decimal("20525272195909974311677173484301099561025532568381820845650748498800315498040161314197178524020516408371544778243934"),
+            priv_key: decimal("100266970209474387075"),
+        })
+    }
+    
     pub fn p() -> BigUint {
-        BigUint::parse_bytes(
-            b"28688293616765795404141427476803815352899912533728694325464374376776313457785622361119232589082131818578591461837297",
-            10
-        ).unwrap()
+        Self::cached().p.clone()
     }
     
     pub fn n() -> BigUint {
-        BigUint::parse_bytes(b"675048016158598417213", 10).unwrap()
+        Self::cached().n.clone()
     }
     
     pub fn gx() -> BigUint {
-        BigUint::parse_bytes(
-            b"18999816458520350299014628291870504329073391058325678653840191278128672378485029664052827205905352913351648904170809",
-            10
-        ).unwrap()
+        Self::cached().gx.clone()
     }
     
     pub fn gy() -> BigUint {
-        BigUint::parse_bytes(
-            b"7233699725243644729688547165924232430035643592445942846958231777803539836627943189850381859836033366776176689124317",
-            10
-        ).unwrap()
+        Self::cached().gy.clone()
     }
     
     pub fn kx() -> BigUint {
-        BigUint::parse_bytes(
-            b"7147768390112741602848314103078506234267895391544114241891627778383312460777957307647946308927283757886117119137500",
-            10
-        ).unwrap()
+        Self::cached().kx.clone()
     }
     
     pub fn ky() -> BigUint {
-        BigUint::parse_bytes(
-            b"20525272195909974311677173484301099561025532568381820845650748498800315498040161314197178524020516408371544778243934",
-            10
-        ).unwrap()
+        Self::cached().ky.clone()
     }
     
     pub fn priv_key() -> BigUint {
-        BigUint::parse_bytes(b"100266970209474387075", 10).unwrap()
+        Self::cached().priv_key.clone()
+    }
+    
+    pub fn params() -> CurveParams {
+        Self::cached().clone()
     }
 }
 
 /// License information parsed from license type string
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, Deserialize))]
 pub struct LicenseInfo {
     pub chid: u32,
     pub major_ver: u32,
@@ -147,7 +387,17 @@ pub struct LicenseInfo {
 }
 
 impl LicenseInfo {
+    /// Parse one of the codes in `LICENSE_TYPES`
     pub fn parse(license_type: &str) -> anyhow::Result<Self> {
+        if !LICENSE_TYPES.iter().any(|(code, _)| *code == license_type) {
+            anyhow::bail!("Unknown license type");
+        }
+        Self::parse_raw(license_type)
+    }
+    
+    /// Parse any `CHID_MAJOR_MINOR` code; codes missing from `LICENSE_TYPES` get a
+    /// description like `Custom (chid=35)`
+    pub fn parse_raw(license_type: &str) -> anyhow::Result<Self> {
         let parts: Vec<&str> = license_type.split('_').collect();
         if parts.len() != 3 {
             anyhow::bail!("License format must be CHID_MAJOR_MINOR (e.g., 029_10_2)");
@@ -157,11 +407,8 @@ impl LicenseInfo {
         let major_ver = parts[1].parse::<u32>()?;
         let minor_ver = parts[2].parse::<u32>()?;
         
-        let description = LICENSE_TYPES
-            .iter()
-            .find(|(code, _)| *code == license_type)
-            .map(|(_, desc)| desc.to_string())
-            .ok_or_else(|| anyhow::anyhow!("Unknown license type"))?;
+        let description = describe_license(chid, major_ver, minor_ver)
+            .map_or_else(|| format!("Custom (chid={})", chid), str::to_string);
         
         Ok(Self {
             chid,
@@ -171,3 +418,106 @@ impl LicenseInfo {
         })
     }
 }
+
+/// Keys generated for one PID, for storing generation batches
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, Deserialize))]
+pub struct GenerationResult {
+    pub pid: String,
+    /// `None` when only an LKP was generated
+    pub spk: Option<String>,
+    pub lkp: Option<String>,
+    /// License of `lkp`
+    pub license: Option<LicenseInfo>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_builtin_curve_params() {
+        for curve in [SPKCurve::params(), LKPCurve::params()] {
+            curve.validate().unwrap();
+            let k = curve.generator().mul(&curve.priv_key);
+            assert_eq!((k.x, k.y), (curve.kx.clone(), curve.ky.clone()));
+        }
+    }
+
+    #[test]
+    fn test_license_info_parse_raw() {
+        let known = LicenseInfo::parse_raw("029_10_2").unwrap();
+        assert_eq!(known.description, "Windows Server 2022 Per Device");
+        
+        let custom = LicenseInfo::parse_raw("035_10_3").unwrap();
+        assert_eq!((custom.chid, custom.major_ver, custom.minor_ver), (35, 10, 3));
+        assert_eq!(custom.description, "Custom (chid=35)");
+        assert!(LicenseInfo::parse("035_10_3").is_err());
+        assert!(LicenseInfo::parse_raw("035_10").is_err());
+    }
+
+    #[test]
+    fn test_describe_license() {
+        assert_eq!(license_code(29, 10, 2), "029_10_2");
+        assert_eq!(describe_license(29, 10, 2), Some("Windows Server 2022 Per Device"));
+        assert_eq!(describe_license(1, 5, 0), Some("Windows 2000 Per Device"));
+        assert_eq!(describe_license(29, 10, 3), None);
+        assert_eq!(describe_license(900, 10, 2), None);
+    }
+
+    #[test]
+    fn test_key_kind() {
+        assert_eq!(KeyKind::from_is_spk(true), KeyKind::Spk);
+        assert_eq!(KeyKind::from_is_spk(false), KeyKind::Lkp);
+        for kind in [KeyKind::Spk, KeyKind::Lkp] {
+            assert_eq!(kind.name().parse::<KeyKind>().unwrap(), kind);
+        }
+        assert!("pkey".parse::<KeyKind>().is_err());
+        assert_eq!(CurveParams::for_kind(KeyKind::Lkp), LKPCurve::params());
+    }
+
+    #[test]
+    fn test_find_license() {
+        assert_eq!(find_license("029_10_2").unwrap().0, "029_10_2");
+        assert_eq!(find_license("2022 per device").unwrap().0, "029_10_2");
+        assert_eq!(find_license(" Windows 2000 Internet ").unwrap().0, "002_5_0");
+        assert!(find_license("Windows 95").is_err());
+        
+        let err = find_license("2025").unwrap_err().to_string();
+        assert!(err.contains("032_10_3") && err.contains("033_10_3") && err.contains("034_10_3"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_generation_result_round_trip() {
+        let result = GenerationResult {
+            pid: "00490-92005-99454-AT527".to_string(),
+            spk: None,
+            lkp: Some("G9QC6-27GXG-XXPGK-GT8D9-D9WH9-K94YQ-7H77B".to_string()),
+            license: Some(LicenseInfo::parse("029_10_2").unwrap()),
+        };
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["license"]["chid"], 29);
+        assert_eq!(json["license"]["major_ver"], 10);
+        assert_eq!(serde_json::from_value::<GenerationResult>(json).unwrap(), result);
+        assert_eq!(toml::from_str::<GenerationResult>(&toml::to_string(&result).unwrap()).unwrap(), result);
+    }
+
+    #[test]
+    fn test_curve_file_parse() {
+        let spk = SPKCurve::params();
+        let contents = format!(
+            "[spk]\np = \"{}\"\nn = \"{}\"\na = \"{}\"\nb = \"{}\"\ngx = \"{}\"\ngy = \"{}\"\nkx = \"{}\"\nky = \"{}\"\npriv_key = \"{}\"\n",
+            spk.p, spk.n, spk.a, spk.b, spk.gx, spk.gy, spk.kx, spk.ky, spk.priv_key
+        );
+        
+        let file = CurveFile::parse(&contents).unwrap();
+        assert_eq!(file.spk, Some(spk));
+        assert!(file.lkp.is_none());
+        
+        // Moving the generator off the curve is rejected
+        let bad = contents.replace("gx = \"", "gx = \"1");
+        assert!(CurveFile::parse(&bad).is_err());
+    }
+}
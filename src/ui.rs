@@ -0,0 +1,48 @@
+//! Helpers shared by the GUI and TUI
+
+use lyssa_rds_gen::keygen::KeyDiagnosis;
+
+/// Labeled block of whichever keys are present, for pasting into a ticket or config
+///
+/// The license description, if any, is added as a leading `#` comment line.
+pub fn format_key_block(spk: &str, lkp: &str, description: &str) -> String {
+    let mut block = String::new();
+    if !description.is_empty() {
+        block.push_str(&format!("# {}\n", description));
+    }
+    if !spk.is_empty() {
+        block.push_str(&format!("SPK: {}\n", spk));
+    }
+    if !lkp.is_empty() {
+        block.push_str(&format!("LKP: {}\n", lkp));
+    }
+    block
+}
+
+/// Status line for a UI validation: `valid` on success, otherwise `invalid`
+/// followed by why the key was rejected
+pub fn validation_status(diagnosis: KeyDiagnosis, valid: &str, invalid: &str) -> String {
+    match diagnosis {
+        KeyDiagnosis::Valid => valid.to_string(),
+        KeyDiagnosis::Malformed(e) => format!("Error: {}", e),
+        diagnosis => format!("{}: {}", invalid, diagnosis),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validation_status() {
+        assert_eq!(validation_status(KeyDiagnosis::Valid, "ok", "bad"), "ok");
+        assert_eq!(
+            validation_status(KeyDiagnosis::Malformed("too short".to_string()), "ok", "bad"),
+            "Error: too short"
+        );
+        assert_eq!(
+            validation_status(KeyDiagnosis::SpkidMismatch { key_spkid: 7, pid_spkid: 5 }, "ok", "bad"),
+            "bad: SPKID mismatch (key carries 7, PID gives 5)"
+        );
+    }
+}
@@ -0,0 +1,39 @@
+//! WebAssembly bindings for browser use
+//!
+//! Build with `wasm-pack build --target web --no-default-features --features wasm`.
+
+use crate::keygen;
+use crate::types::{CurveParams, KeyKind, LicenseInfo};
+use wasm_bindgen::prelude::*;
+
+fn to_js_error(e: anyhow::Error) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+/// Generate SPK (License Server ID) for a Product ID
+#[wasm_bindgen(js_name = generateSpk)]
+pub fn generate_spk(pid: &str) -> Result<String, JsError> {
+    keygen::generate_spk(&keygen::normalize_pid(pid)).map_err(to_js_error)
+}
+
+/// Generate LKP (License Key Pack) for a Product ID, count and license code (e.g., `029_10_2`)
+#[wasm_bindgen(js_name = generateLkp)]
+pub fn generate_lkp(pid: &str, count: u32, license: &str) -> Result<String, JsError> {
+    let license_info = LicenseInfo::parse(license).map_err(to_js_error)?;
+    keygen::generate_lkp(
+        &keygen::normalize_pid(pid),
+        count,
+        license_info.chid,
+        license_info.major_ver,
+        license_info.minor_ver,
+    )
+    .map_err(to_js_error)
+}
+
+/// Validate an SPK (`isSpk = true`) or LKP (`isSpk = false`) against a Product ID
+#[wasm_bindgen(js_name = validateTskey)]
+pub fn validate_tskey(pid: &str, tskey: &str, is_spk: bool) -> Result<bool, JsError> {
+    let pid = keygen::normalize_pid(pid);
+    let kind = KeyKind::from_is_spk(is_spk);
+    keygen::validate_tskey(&pid, tskey, &CurveParams::for_kind(kind), kind).map_err(to_js_error)
+}
@@ -0,0 +1,111 @@
+//! Known-answer tests pinning SPKID extraction and seeded SPK/LKP output
+//!
+//! The vectors were produced by running `generate_tskey` with
+//! `StdRng::seed_from_u64(seed)` on the builtin curves, then checking every
+//! key with `validate_tskey` from the Python reference (`lyssa_rds_gen.py`).
+//! LKP vectors use license `029_10_2` (CHID 29, version 10.2) with a count
+//! of 100. Any change to nonce derivation, hashing, signing or encoding shows
+//! up here as a mismatch.
+//!
+//! `StdRng` is only reproducible within a `rand` major version; if `rand` is
+//! upgraded, regenerate the vectors and re-check them against the reference.
+//! The RFC 6979 vectors do not depend on `rand` at all, so they should never
+//! need regenerating.
+
+use lyssa_rds_gen::crypto::bigint_to_bytes_le;
+use lyssa_rds_gen::keygen::{
+    generate_lkp_rfc6979, generate_spk_rfc6979, generate_tskey, get_spkid, validate_tskey, DEFAULT_MAX_ATTEMPTS,
+};
+use lyssa_rds_gen::types::{KeyKind, LKPCurve, SPKCurve};
+use num_bigint::BigUint;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// (PID, expected SPKID)
+const SPKID_VECTORS: &[(&str, u64)] = &[
+    ("00490-92005-99454-AT527", 5),
+    ("00431-10000-00000-AA000", 0),
+    ("00490-12345-67890-AT123", 5),
+];
+
+/// (PID, seed, expected SPK)
+const SPK_VECTORS: &[(&str, u64, &str)] = &[
+    ("00490-92005-99454-AT527", 0, "MD8RV-XTYKC-2PYWT-KPCW4-WVCD9-4DTJR-8GPXH"),
+    ("00490-92005-99454-AT527", 42, "K284Q-94Q4M-JMB3K-WVRXK-PRP43-8HKX9-HGY27"),
+    ("00431-10000-00000-AA000", 0, "HQKFR-6TH73-DR826-2JCGY-WWHBQ-JCQWX-CWR39"),
+    ("00431-10000-00000-AA000", 42, "QDTP7-RRWXD-TF23M-66YV3-2DK24-YYYV9-39YHX"),
+    ("00490-12345-67890-AT123", 0, "QYY4C-YTRVQ-T8C9T-F32KF-JPBRF-KT344-KKPXF"),
+    ("00490-12345-67890-AT123", 42, "QCHWM-Y24BM-FCV2P-H72WJ-2BR8W-7XYCT-TVB24"),
+];
+
+/// (PID, seed, expected LKP) for 100 x `029_10_2`
+const LKP_VECTORS: &[(&str, u64, &str)] = &[
+    ("00490-92005-99454-AT527", 0, "HGK92-7WG9H-WFKQ3-JDVF9-638R9-7B8XK-HVBVB"),
+    ("00490-92005-99454-AT527", 42, "J2YVM-FJV6Q-9JDX8-QJRQ2-B6KX9-BYQPB-V8TDM"),
+    ("00431-10000-00000-AA000", 0, "CTPQ4-J6KMT-MXR4G-RQPK8-793JC-BMJKR-JPP4X"),
+    ("00431-10000-00000-AA000", 42, "F6MX6-9PHTP-HDHFG-YP3MQ-97W4D-D7D8J-KDBB9"),
+    ("00490-12345-67890-AT123", 0, "RTQYQ-PJCDX-C6MKH-2MFGG-6VKTW-9TW6J-9MT98"),
+    ("00490-12345-67890-AT123", 42, "PB9XQ-WQTQ4-W7XFC-V8TGG-GPC9C-JHWFM-K3BVJ"),
+];
+
+/// (PID, expected SPK, expected LKP for 100 x `029_10_2`) with RFC 6979 nonces
+const RFC6979_VECTORS: &[(&str, &str, &str)] = &[
+    (
+        "00490-92005-99454-AT527",
+        "RPBTP-TRC93-GGT7X-HWTQM-JKV7X-FQCRM-C22XH",
+        "YB724-6CWV9-RY3TW-VM6P9-6RFYT-8M7MP-VXPQM",
+    ),
+    (
+        "00431-10000-00000-AA000",
+        "X8XDQ-YBJ74-XKFMF-P4RR2-D3FR8-GXHMP-G9DBK",
+        "TM8WG-YBMJV-8VWG7-XD4GK-3HGW8-4982V-VY4WK",
+    ),
+];
+
+/// LKP info for 100 x `029_10_2`, laid out as in `generate_lkp`
+const LKP_INFO: u64 = (29 << 46) | (100 << 32) | (2 << 18) | (144 << 10) | (((10 << 3) | 2) << 3);
+
+#[test]
+fn test_spkid_vectors() {
+    for &(pid, spkid) in SPKID_VECTORS {
+        assert_eq!(get_spkid(pid).unwrap(), spkid, "SPKID for {}", pid);
+    }
+}
+
+#[test]
+fn test_spk_vectors() {
+    let curve = SPKCurve::params();
+    for &(pid, seed, expected) in SPK_VECTORS {
+        let spkdata = bigint_to_bytes_le(&BigUint::from(get_spkid(pid).unwrap()), 7).unwrap();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let spk = generate_tskey(pid, &spkdata, &curve, KeyKind::Spk, DEFAULT_MAX_ATTEMPTS, &mut rng).unwrap();
+        assert_eq!(spk, expected, "SPK for {} with seed {}", pid, seed);
+        assert!(validate_tskey(pid, expected, &curve, KeyKind::Spk).unwrap());
+    }
+}
+
+#[test]
+fn test_lkp_vectors() {
+    let curve = LKPCurve::params();
+    let lkpdata = bigint_to_bytes_le(&BigUint::from(LKP_INFO), 7).unwrap();
+    for &(pid, seed, expected) in LKP_VECTORS {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let lkp = generate_tskey(pid, &lkpdata, &curve, KeyKind::Lkp, DEFAULT_MAX_ATTEMPTS, &mut rng).unwrap();
+        assert_eq!(lkp, expected, "LKP for {} with seed {}", pid, seed);
+        assert!(validate_tskey(pid, expected, &curve, KeyKind::Lkp).unwrap());
+    }
+}
+
+#[test]
+fn test_rfc6979_vectors() {
+    let (spk_curve, lkp_curve) = (SPKCurve::params(), LKPCurve::params());
+    for &(pid, expected_spk, expected_lkp) in RFC6979_VECTORS {
+        let spk = generate_spk_rfc6979(pid, &spk_curve, DEFAULT_MAX_ATTEMPTS, None).unwrap();
+        assert_eq!(spk, expected_spk, "SPK for {}", pid);
+        assert!(validate_tskey(pid, expected_spk, &spk_curve, KeyKind::Spk).unwrap());
+
+        let lkp = generate_lkp_rfc6979(pid, 100, 29, 10, 2, &lkp_curve, DEFAULT_MAX_ATTEMPTS, None).unwrap();
+        assert_eq!(lkp, expected_lkp, "LKP for {}", pid);
+        assert!(validate_tskey(pid, expected_lkp, &lkp_curve, KeyKind::Lkp).unwrap());
+    }
+}